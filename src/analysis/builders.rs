@@ -0,0 +1,197 @@
+use super::report::{ratio, ReportRow};
+use crate::{data::event::Event, refunds::refund::decimal};
+use ethers::types::U256;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Order of the builder leaderboard, best first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LeaderboardSort {
+    /// Most MEV-Share transactions included.
+    #[default]
+    Included,
+    /// Most refunds.
+    Refunds,
+    /// Most refunded wei.
+    Refunded,
+    /// Lowest average refund latency.
+    Latency,
+}
+
+/// MEV-Share transactions included by each builder and the refunds they paid.
+#[derive(Debug, Clone, Default)]
+pub struct BuilderLeaderboard {
+    builders: HashMap<String, BuilderTotals>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct BuilderTotals {
+    included: u64,
+    refunded: u64,
+    refunds: u64,
+    total_refunded: U256,
+    /// Sum of the refund latencies of the refunded events.
+    latency_blocks: u64,
+}
+
+/// Leaderboard entry of one builder.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BuilderRow {
+    pub rank: u64,
+    /// Builder name, or the fee recipient address of unknown builders.
+    pub builder: String,
+    /// Landed MEV-Share transactions.
+    pub included: u64,
+    /// Included transactions with at least one refund.
+    pub refunded: u64,
+    pub refunds: u64,
+    pub refund_rate: f64,
+    #[serde(with = "decimal")]
+    pub total_refunded: U256,
+    /// Average number of blocks from the hint to the refunded landing.
+    pub avg_refund_latency: Option<f64>,
+}
+
+impl BuilderLeaderboard {
+    /// Adds `event` to the totals of the builder it landed with. Events that did not land are
+    /// skipped.
+    pub fn add(&mut self, event: &Event) {
+        let Some(landing) = event
+            .landing
+            .as_ref()
+            .filter(|_| event.landed == Some(true))
+        else {
+            return;
+        };
+        let builder = landing
+            .builder_name
+            .clone()
+            .unwrap_or_else(|| format!("{:?}", landing.builder));
+        let totals = self.builders.entry(builder).or_default();
+        totals.included += 1;
+        if !event.refunds.is_empty() {
+            totals.refunded += 1;
+            totals.refunds += event.refunds.len() as u64;
+            totals.total_refunded = totals.total_refunded.saturating_add(event.refunded());
+            totals.latency_blocks += landing.block.saturating_sub(event.block);
+        }
+    }
+
+    /// Builders ordered by `sort`, ties broken by name.
+    pub fn rows(self, sort: LeaderboardSort) -> Vec<BuilderRow> {
+        let mut rows: Vec<BuilderRow> = self
+            .builders
+            .into_iter()
+            .map(|(builder, totals)| BuilderRow {
+                rank: 0,
+                builder,
+                included: totals.included,
+                refunded: totals.refunded,
+                refunds: totals.refunds,
+                refund_rate: ratio(totals.refunded, totals.included),
+                total_refunded: totals.total_refunded,
+                avg_refund_latency: (totals.refunded > 0)
+                    .then(|| totals.latency_blocks as f64 / totals.refunded as f64),
+            })
+            .collect();
+        rows.sort_by(|a, b| {
+            let order = match sort {
+                LeaderboardSort::Included => b.included.cmp(&a.included),
+                LeaderboardSort::Refunds => b.refunds.cmp(&a.refunds),
+                LeaderboardSort::Refunded => b.total_refunded.cmp(&a.total_refunded),
+                // Builders without refunds rank last.
+                LeaderboardSort::Latency => {
+                    let latency = |row: &BuilderRow| row.avg_refund_latency.unwrap_or(f64::MAX);
+                    latency(a).total_cmp(&latency(b))
+                }
+            };
+            order.then_with(|| a.builder.cmp(&b.builder))
+        });
+        for (i, row) in rows.iter_mut().enumerate() {
+            row.rank = i as u64 + 1;
+        }
+        rows
+    }
+}
+
+impl ReportRow for BuilderRow {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "rank",
+            "builder",
+            "included",
+            "refunded",
+            "refunds",
+            "refund_rate",
+            "total_refunded",
+            "avg_refund_latency",
+        ]
+    }
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.rank.to_string(),
+            self.builder.clone(),
+            self.included.to_string(),
+            self.refunded.to_string(),
+            self.refunds.to_string(),
+            format!("{:.4}", self.refund_rate),
+            self.total_refunded.to_string(),
+            self.avg_refund_latency
+                .map(|latency| format!("{:.2}", latency))
+                .unwrap_or_default(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis::test_event, refunds::landing::Landing};
+    use ethers::types::Address;
+
+    fn event(builder_name: Option<&str>, landing_block: u64, refunds: &[u64]) -> Event {
+        let mut event = test_event(100, 1, refunds);
+        event.landed = Some(true);
+        event.landing = Some(Landing {
+            block: landing_block,
+            builder: Address::zero(),
+            builder_name: builder_name.map(String::from),
+            ..Default::default()
+        });
+        event
+    }
+
+    #[test]
+    fn test_leaderboard() {
+        let mut leaderboard = BuilderLeaderboard::default();
+        for event in [
+            event(Some("flashbots"), 101, &[10]),
+            event(Some("flashbots"), 104, &[10, 5]),
+            event(Some("flashbots"), 101, &[]),
+            event(None, 102, &[100]),
+        ] {
+            leaderboard.add(&event);
+        }
+
+        let rows = leaderboard.clone().rows(LeaderboardSort::Included);
+        assert_eq!(
+            rows[0],
+            BuilderRow {
+                rank: 1,
+                builder: "flashbots".into(),
+                included: 3,
+                refunded: 2,
+                refunds: 3,
+                refund_rate: 2.0 / 3.0,
+                total_refunded: U256::from(25),
+                avg_refund_latency: Some(2.5),
+            }
+        );
+        assert_eq!(rows[1].builder, format!("{:?}", Address::zero()));
+
+        let rows = leaderboard.rows(LeaderboardSort::Latency);
+        assert_eq!(rows[0].avg_refund_latency, Some(2.0));
+        assert_eq!(rows[1].builder, "flashbots");
+    }
+}
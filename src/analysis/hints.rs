@@ -0,0 +1,204 @@
+use super::report::{median, ratio, ReportRow};
+use crate::{
+    data::{event::Event, profile::DisclosureProfile},
+    refunds::refund::decimal,
+};
+use ethers::types::U256;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Landing and refund outcomes of stored events, grouped by disclosure profile.
+#[derive(Debug, Clone, Default)]
+pub struct HintReport {
+    /// Totals per profile, with events whose profile is unknown under `None`.
+    profiles: BTreeMap<Option<DisclosureProfile>, ProfileTotals>,
+}
+
+/// Profile name of the row of events whose profile is unknown.
+const UNCLASSIFIED: &str = "unclassified";
+
+#[derive(Debug, Clone, Default)]
+struct ProfileTotals {
+    events: u64,
+    scanned: u64,
+    landed: u64,
+    /// Total refunded wei of each refunded event.
+    refunded: Vec<U256>,
+}
+
+/// Outcomes of the events of one disclosure profile.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HintRow {
+    pub profile: String,
+    pub events: u64,
+    /// Events whose landing was scanned, the denominator of `landing_rate`.
+    pub scanned: u64,
+    pub landed: u64,
+    /// Landed events with at least one refund.
+    pub refunded: u64,
+    /// Share of scanned events that landed.
+    pub landing_rate: f64,
+    /// Share of landed events that were refunded.
+    pub refund_rate: f64,
+    /// Median refunded wei of the refunded events.
+    #[serde(with = "decimal")]
+    pub median_refund: U256,
+    #[serde(with = "decimal")]
+    pub total_refunded: U256,
+}
+
+impl HintReport {
+    /// Adds `event` to the totals of its profile. Events stored without a profile are classified,
+    /// except events received from the live stream, whose hints lack the gas fields; they are
+    /// counted as unclassified.
+    pub fn add(&mut self, event: &Event) {
+        let profile = match (event.disclosure_profile, event.received_at) {
+            (Some(profile), _) => Some(profile),
+            (None, None) => Some(DisclosureProfile::classify(&event.hint)),
+            (None, Some(_)) => None,
+        };
+        let totals = self.profiles.entry(profile).or_default();
+        totals.events += 1;
+        let Some(landed) = event.landed else {
+            return;
+        };
+        totals.scanned += 1;
+        if landed {
+            totals.landed += 1;
+            if !event.refunds.is_empty() {
+                totals.refunded.push(event.refunded());
+            }
+        }
+    }
+
+    /// One row per profile, most common first.
+    pub fn rows(self) -> Vec<HintRow> {
+        let mut rows: Vec<HintRow> = self
+            .profiles
+            .into_iter()
+            .map(|(profile, mut totals)| HintRow {
+                profile: profile.map_or(UNCLASSIFIED.into(), |profile| profile.to_string()),
+                events: totals.events,
+                scanned: totals.scanned,
+                landed: totals.landed,
+                refunded: totals.refunded.len() as u64,
+                landing_rate: ratio(totals.landed, totals.scanned),
+                refund_rate: ratio(totals.refunded.len() as u64, totals.landed),
+                median_refund: median(&mut totals.refunded).unwrap_or_default(),
+                total_refunded: totals
+                    .refunded
+                    .iter()
+                    .fold(U256::zero(), |total, value| total.saturating_add(*value)),
+            })
+            .collect();
+        rows.sort_by_key(|row| std::cmp::Reverse(row.events));
+        rows
+    }
+}
+
+impl ReportRow for HintRow {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "profile",
+            "events",
+            "scanned",
+            "landed",
+            "refunded",
+            "landing_rate",
+            "refund_rate",
+            "median_refund",
+            "total_refunded",
+        ]
+    }
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.profile.clone(),
+            self.events.to_string(),
+            self.scanned.to_string(),
+            self.landed.to_string(),
+            self.refunded.to_string(),
+            format!("{:.4}", self.landing_rate),
+            format!("{:.4}", self.refund_rate),
+            self.median_refund.to_string(),
+            self.total_refunded.to_string(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_event;
+    use ethers::types::{H160, H256};
+    use mev_share::sse::{self, EventTransaction};
+
+    fn event(mev_gas_price: bool, landed: Option<bool>, refunds: &[u64]) -> Event {
+        let mut event = test_event(1, 1, refunds);
+        event.hint.mev_gas_price = mev_gas_price.then(U256::one);
+        event.disclosure_profile = Some(DisclosureProfile::classify(&event.hint));
+        event.landed = landed;
+        event
+    }
+
+    #[test]
+    fn test_hint_report() {
+        let mut report = HintReport::default();
+        for event in [
+            event(true, Some(true), &[100, 50]),
+            event(true, Some(true), &[]),
+            event(true, Some(true), &[300]),
+            event(true, Some(false), &[]),
+            event(true, None, &[]),
+            event(false, Some(false), &[]),
+        ] {
+            report.add(&event);
+        }
+
+        let rows = report.rows();
+        assert_eq!(
+            rows[0],
+            HintRow {
+                profile: "mev_gas_price".into(),
+                events: 5,
+                scanned: 4,
+                landed: 3,
+                refunded: 2,
+                landing_rate: 0.75,
+                refund_rate: 2.0 / 3.0,
+                median_refund: U256::from(225),
+                total_refunded: U256::from(450),
+            }
+        );
+        assert_eq!(rows[1].profile, "hash_only");
+        assert_eq!(rows[1].landing_rate, 0.0);
+    }
+
+    #[test]
+    fn test_hint_report_of_stream_events() {
+        let tx = EventTransaction {
+            to: Some(H160::random()),
+            function_selector: None,
+            calldata: None,
+        };
+        let streamed = Event::from_stream(
+            sse::Event {
+                hash: H256::random(),
+                transactions: vec![tx.clone()],
+                logs: vec![],
+            },
+            1,
+            1_000,
+        );
+        // Stored before profiles were recorded.
+        let mut legacy = test_event(1, 1, &[]);
+        legacy.hint.txs.push(tx);
+        legacy.disclosure_profile = None;
+
+        let mut report = HintReport::default();
+        report.add(&streamed);
+        report.add(&legacy);
+        let profiles: Vec<_> = report.rows().into_iter().map(|row| row.profile).collect();
+        assert_eq!(profiles, vec!["unclassified", "contract_address"]);
+    }
+}
@@ -0,0 +1,34 @@
+pub mod builders;
+pub mod hints;
+pub mod profile;
+pub mod report;
+pub mod stats;
+pub mod timeseries;
+
+/// Event at `block` and `timestamp` with a random hint hash, refunded `refunds` wei.
+#[cfg(test)]
+pub(crate) fn test_event(block: u64, timestamp: u64, refunds: &[u64]) -> crate::data::event::Event {
+    use crate::{data::event::Event, refunds::refund::Refund};
+    use ethers::types::{H256, U256};
+    use mev_share::sse::{EventHistory, Hint};
+
+    let mut event = Event::new(EventHistory {
+        block,
+        timestamp,
+        hint: Hint {
+            hash: H256::random(),
+            txs: vec![],
+            logs: vec![],
+            mev_gas_price: None,
+            gas_used: None,
+        },
+    });
+    event.refunds = refunds
+        .iter()
+        .map(|value| Refund {
+            value: U256::from(*value),
+            ..Default::default()
+        })
+        .collect();
+    event
+}
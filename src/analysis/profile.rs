@@ -0,0 +1,91 @@
+use crate::{
+    data::{
+        profile::DisclosureProfile,
+        store::{EventFilter, EventStore, EventUpdate},
+    },
+    error::Result,
+};
+use futures::StreamExt;
+
+/// Classifies the events matching `filter` and persists their profiles. Returns the number of
+/// events classified.
+///
+/// Events received from the live stream without a profile are skipped, as their hints lack the
+/// gas fields and would be classified as not revealing them.
+pub async fn classify_events<S: EventStore>(store: &S, filter: &EventFilter) -> Result<u64> {
+    // Read all hashes first, as updates may move events out of an `unclassified` filter.
+    let mut events = store.read_events(filter).await?;
+    let mut profiles = vec![];
+    while let Some(event) = events.next().await {
+        let event = event?;
+        if event.received_at.is_some() && event.disclosure_profile.is_none() {
+            continue;
+        }
+        profiles.push((event.hint.hash, DisclosureProfile::classify(&event.hint)));
+    }
+    drop(events);
+
+    for (hash, profile) in &profiles {
+        let update = EventUpdate {
+            disclosure_profile: Some(*profile),
+            ..Default::default()
+        };
+        store.update_event(*hash, update).await?;
+    }
+    Ok(profiles.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{event::Event, sqlite::SqliteStore};
+    use ethers::types::H256;
+    use mev_share::sse::{self, EventHistory, Hint};
+
+    #[tokio::test]
+    async fn test_classify_events() {
+        let store = SqliteStore::in_memory().unwrap();
+        let mut event = Event::new(EventHistory {
+            block: 1,
+            timestamp: 1,
+            hint: Hint {
+                hash: H256::random(),
+                txs: vec![],
+                logs: vec![],
+                mev_gas_price: Some(1.into()),
+                gas_used: None,
+            },
+        });
+        // As stored before profiles were classified.
+        event.disclosure_profile = None;
+        let streamed = Event::from_stream(
+            sse::Event {
+                hash: H256::random(),
+                transactions: vec![],
+                logs: vec![],
+            },
+            1,
+            1_000,
+        );
+        store.write_events(vec![event, streamed]).await.unwrap();
+
+        let unclassified = EventFilter {
+            unclassified: true,
+            ..Default::default()
+        };
+        assert_eq!(classify_events(&store, &unclassified).await.unwrap(), 1);
+        assert_eq!(store.count(&unclassified).await.unwrap(), 1);
+        let event = store
+            .read_events(&EventFilter::default())
+            .await
+            .unwrap()
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            event.disclosure_profile.map(|profile| profile.to_string()),
+            Some("mev_gas_price".into())
+        );
+    }
+}
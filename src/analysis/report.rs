@@ -0,0 +1,150 @@
+use crate::error::{Error, Result};
+use ethers::types::U256;
+use serde::Serialize;
+
+/// Output format of a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ReportFormat {
+    /// Aligned columns, for reading in a terminal.
+    #[default]
+    Table,
+    Csv,
+    /// A JSON array with one object per row.
+    Json,
+}
+
+/// Row of a report, rendered as one line of a table or CSV file and as one JSON object.
+pub trait ReportRow: Serialize {
+    fn headers() -> Vec<&'static str>;
+    /// Cells of the row, in the order of [`ReportRow::headers`].
+    fn cells(&self) -> Vec<String>;
+}
+
+/// Renders `rows` in `format`.
+pub fn render<R: ReportRow>(rows: &[R], format: ReportFormat) -> Result<String> {
+    let headers: Vec<String> = R::headers().into_iter().map(String::from).collect();
+    let cells: Vec<Vec<String>> = rows.iter().map(ReportRow::cells).collect();
+    match format {
+        ReportFormat::Table => Ok(render_table(&headers, &cells)),
+        ReportFormat::Csv => Ok(render_csv(&headers, &cells)),
+        ReportFormat::Json => {
+            serde_json::to_string_pretty(rows).map_err(|error| Error::decode("report", error))
+        }
+    }
+}
+
+fn render_table(headers: &[String], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(String::len).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let line = |cells: &[String]| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        padded.join("  ").trim_end().to_string()
+    };
+    let separator: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    let mut lines = vec![line(headers), line(&separator)];
+    lines.extend(rows.iter().map(|row| line(row)));
+    lines.join("\n")
+}
+
+fn render_csv(headers: &[String], rows: &[Vec<String>]) -> String {
+    let escape = |cell: &String| {
+        if cell.contains([',', '"', '\n']) {
+            format!("\"{}\"", cell.replace('"', "\"\""))
+        } else {
+            cell.clone()
+        }
+    };
+    std::iter::once(headers)
+        .chain(rows.iter().map(Vec::as_slice))
+        .map(|row| row.iter().map(escape).collect::<Vec<_>>().join(","))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `part / whole`, or 0 when `whole` is 0.
+pub fn ratio(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+/// Median of `values`, averaging the two middle values for an even count. Sorts `values`.
+pub fn median(values: &mut [U256]) -> Option<U256> {
+    values.sort();
+    let mid = values.len() / 2;
+    match values.len() {
+        0 => None,
+        len if len % 2 == 1 => Some(values[mid]),
+        _ => {
+            let (a, b) = (values[mid - 1], values[mid]);
+            // Halved separately so the sum cannot overflow.
+            Some(a / 2 + b / 2 + (a % 2 + b % 2) / 2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Row {
+        name: String,
+        count: u64,
+    }
+
+    impl ReportRow for Row {
+        fn headers() -> Vec<&'static str> {
+            vec!["name", "count"]
+        }
+
+        fn cells(&self) -> Vec<String> {
+            vec![self.name.clone(), self.count.to_string()]
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let rows = vec![
+            Row {
+                name: "a,b".into(),
+                count: 1,
+            },
+            Row {
+                name: "c".into(),
+                count: 20,
+            },
+        ];
+        assert_eq!(
+            render(&rows, ReportFormat::Table).unwrap(),
+            "name  count\n----  -----\na,b   1\nc     20"
+        );
+        assert_eq!(
+            render(&rows, ReportFormat::Csv).unwrap(),
+            "name,count\n\"a,b\",1\nc,20"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&rows, ReportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json[1]["count"], 20);
+    }
+
+    #[test]
+    fn test_median() {
+        let mut values: Vec<U256> = [5, 1, 3].map(U256::from).to_vec();
+        assert_eq!(median(&mut values), Some(U256::from(3)));
+        values.push(U256::from(8));
+        assert_eq!(median(&mut values), Some(U256::from(4)));
+        assert_eq!(median(&mut [U256::MAX, U256::MAX]), Some(U256::MAX));
+        assert_eq!(median(&mut []), None);
+    }
+}
@@ -0,0 +1,159 @@
+use super::report::ReportRow;
+use crate::{data::event::Event, refunds::refund::decimal};
+use ethers::types::{Address, U256};
+use serde::Serialize;
+
+/// Totals of the scan results of stored events.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    pub events: u64,
+    /// Events with a landing scan result.
+    pub scanned: u64,
+    pub landed: u64,
+    /// Landed events whose transaction reverted.
+    pub reverted: u64,
+    /// Landed events with at least one refund.
+    pub refunded: u64,
+    pub refunds: u64,
+    #[serde(with = "decimal")]
+    pub total_refunded: U256,
+}
+
+impl Stats {
+    pub fn add(&mut self, event: &Event) {
+        self.events += 1;
+        let Some(landed) = event.landed else {
+            return;
+        };
+        self.scanned += 1;
+        if !landed {
+            return;
+        }
+        self.landed += 1;
+        if event.landing.as_ref().and_then(|landing| landing.success) == Some(false) {
+            self.reverted += 1;
+        }
+        if !event.refunds.is_empty() {
+            self.refunded += 1;
+            self.refunds += event.refunds.len() as u64;
+            self.total_refunded = self.total_refunded.saturating_add(event.refunded());
+        }
+    }
+}
+
+impl ReportRow for Stats {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "events",
+            "scanned",
+            "landed",
+            "reverted",
+            "refunded",
+            "refunds",
+            "total_refunded",
+        ]
+    }
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.events.to_string(),
+            self.scanned.to_string(),
+            self.landed.to_string(),
+            self.reverted.to_string(),
+            self.refunded.to_string(),
+            self.refunds.to_string(),
+            self.total_refunded.to_string(),
+        ]
+    }
+}
+
+/// Builder of landed events, given as a fee recipient address or a builder name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuilderFilter {
+    Address(Address),
+    /// Matched case-insensitively against the resolved builder name.
+    Name(String),
+}
+
+impl BuilderFilter {
+    pub fn new(builder: &str) -> Self {
+        match builder.parse() {
+            Ok(address) => BuilderFilter::Address(address),
+            Err(_) => BuilderFilter::Name(builder.to_lowercase()),
+        }
+    }
+
+    /// Whether `event` landed in a block of this builder.
+    pub fn matches(&self, event: &Event) -> bool {
+        let Some(landing) = &event.landing else {
+            return false;
+        };
+        match self {
+            BuilderFilter::Address(address) => landing.builder == *address,
+            BuilderFilter::Name(name) => landing
+                .builder_name
+                .as_ref()
+                .is_some_and(|builder_name| builder_name.to_lowercase() == *name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis::test_event, refunds::landing::Landing};
+
+    fn event(landing: Option<Landing>, refunds: &[u64]) -> Event {
+        let mut event = test_event(1, 1, refunds);
+        event.landed = Some(landing.is_some());
+        event.landing = landing;
+        event
+    }
+
+    #[test]
+    fn test_stats() {
+        let builder = Address::random();
+        let landing = Landing {
+            builder,
+            builder_name: Some("flashbots".into()),
+            ..Default::default()
+        };
+        let events = [
+            event(Some(landing.clone()), &[10, 20]),
+            event(
+                Some(Landing {
+                    success: Some(false),
+                    ..Default::default()
+                }),
+                &[],
+            ),
+            event(None, &[]),
+        ];
+
+        let mut stats = Stats::default();
+        events.iter().for_each(|event| stats.add(event));
+        assert_eq!(
+            stats,
+            Stats {
+                events: 3,
+                scanned: 3,
+                landed: 2,
+                reverted: 1,
+                refunded: 1,
+                refunds: 2,
+                total_refunded: U256::from(30),
+            }
+        );
+
+        for filter in [
+            BuilderFilter::new(&format!("{:?}", builder)),
+            BuilderFilter::new("Flashbots"),
+        ] {
+            let matched: Vec<_> = events
+                .iter()
+                .filter(|event| filter.matches(event))
+                .collect();
+            assert_eq!(matched.len(), 1);
+        }
+    }
+}
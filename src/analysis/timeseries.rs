@@ -0,0 +1,249 @@
+use super::report::ReportRow;
+use crate::{data::event::Event, refunds::refund::decimal};
+use ethers::types::U256;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Width of the buckets of a time series. Buckets are aligned to UTC, weeks start on Monday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Interval {
+    Hour,
+    #[default]
+    Day,
+    Week,
+}
+
+const HOUR: u64 = 3_600;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+/// The unix epoch was a Thursday, 3 days after the start of its week.
+const WEEK_OFFSET: u64 = 3 * DAY;
+
+impl Interval {
+    fn seconds(self) -> u64 {
+        match self {
+            Interval::Hour => HOUR,
+            Interval::Day => DAY,
+            Interval::Week => WEEK,
+        }
+    }
+
+    /// Start of the bucket containing unix time `timestamp`.
+    pub fn bucket(self, timestamp: u64) -> u64 {
+        match self {
+            Interval::Week => {
+                let shifted = timestamp + WEEK_OFFSET;
+                (shifted - shifted % WEEK).saturating_sub(WEEK_OFFSET)
+            }
+            _ => timestamp - timestamp % self.seconds(),
+        }
+    }
+}
+
+/// Events, landings and refunds per time bucket. Events are bucketed by the time of their hint,
+/// landings and refunds by the time of the block they landed in.
+#[derive(Debug, Clone)]
+pub struct TimeSeries {
+    interval: Interval,
+    buckets: BTreeMap<u64, BucketTotals>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct BucketTotals {
+    events: u64,
+    landings: u64,
+    refunded: u64,
+    refunds: u64,
+    total_refunded: U256,
+}
+
+/// Totals of one time bucket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TimeSeriesRow {
+    /// Unix time of the start of the bucket.
+    pub timestamp: u64,
+    /// Start of the bucket in UTC, as `YYYY-MM-DD HH:MM`.
+    pub start: String,
+    pub events: u64,
+    pub landings: u64,
+    /// Landings with at least one refund.
+    pub refunded: u64,
+    pub refunds: u64,
+    #[serde(with = "decimal")]
+    pub total_refunded: U256,
+}
+
+impl TimeSeries {
+    pub fn new(interval: Interval) -> Self {
+        TimeSeries {
+            interval,
+            buckets: BTreeMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, event: &Event) {
+        self.bucket(event.timestamp).events += 1;
+        let Some(landing) = event
+            .landing
+            .as_ref()
+            .filter(|_| event.landed == Some(true))
+        else {
+            return;
+        };
+        let totals = self.bucket(landing.timestamp);
+        totals.landings += 1;
+        if !event.refunds.is_empty() {
+            totals.refunded += 1;
+            totals.refunds += event.refunds.len() as u64;
+            totals.total_refunded = totals.total_refunded.saturating_add(event.refunded());
+        }
+    }
+
+    fn bucket(&mut self, timestamp: u64) -> &mut BucketTotals {
+        self.buckets
+            .entry(self.interval.bucket(timestamp))
+            .or_default()
+    }
+
+    /// One row per bucket from the first to the last one with data, in time order. Buckets
+    /// without data in between are included with zero totals.
+    pub fn rows(self) -> Vec<TimeSeriesRow> {
+        let (Some(first), Some(last)) = (
+            self.buckets.keys().next().copied(),
+            self.buckets.keys().last().copied(),
+        ) else {
+            return vec![];
+        };
+        (first..=last)
+            .step_by(self.interval.seconds() as usize)
+            .map(|timestamp| {
+                let totals = self.buckets.get(&timestamp).cloned().unwrap_or_default();
+                TimeSeriesRow {
+                    timestamp,
+                    start: format_utc(timestamp),
+                    events: totals.events,
+                    landings: totals.landings,
+                    refunded: totals.refunded,
+                    refunds: totals.refunds,
+                    total_refunded: totals.total_refunded,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Formats unix time `timestamp` as `YYYY-MM-DD HH:MM` in UTC.
+fn format_utc(timestamp: u64) -> String {
+    // Civil date from days since the epoch, after Howard Hinnant's `civil_from_days`.
+    let days = (timestamp / DAY) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let seconds = timestamp % DAY;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / HOUR,
+        seconds % HOUR / 60
+    )
+}
+
+impl ReportRow for TimeSeriesRow {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "timestamp",
+            "start",
+            "events",
+            "landings",
+            "refunded",
+            "refunds",
+            "total_refunded",
+        ]
+    }
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.timestamp.to_string(),
+            self.start.clone(),
+            self.events.to_string(),
+            self.landings.to_string(),
+            self.refunded.to_string(),
+            self.refunds.to_string(),
+            self.total_refunded.to_string(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis::test_event, refunds::landing::Landing};
+
+    #[test]
+    fn test_buckets() {
+        // Wednesday 2023-11-15 13:45:00 UTC.
+        let timestamp = 1_700_055_900;
+        assert_eq!(
+            format_utc(Interval::Hour.bucket(timestamp)),
+            "2023-11-15 13:00"
+        );
+        assert_eq!(
+            format_utc(Interval::Day.bucket(timestamp)),
+            "2023-11-15 00:00"
+        );
+        assert_eq!(
+            format_utc(Interval::Week.bucket(timestamp)),
+            "2023-11-13 00:00"
+        );
+        assert_eq!(format_utc(0), "1970-01-01 00:00");
+        assert_eq!(format_utc(951_782_400), "2000-02-29 00:00");
+    }
+
+    #[test]
+    fn test_time_series() {
+        let event = |timestamp: u64, landing_timestamp: Option<u64>, refunds: &[u64]| {
+            let mut event = test_event(1, timestamp, refunds);
+            event.landed = Some(landing_timestamp.is_some());
+            event.landing = landing_timestamp.map(|timestamp| Landing {
+                timestamp,
+                ..Default::default()
+            });
+            event
+        };
+
+        let mut series = TimeSeries::new(Interval::Day);
+        // A hint late on day 0 that landed on day 1, and one on day 3.
+        series.add(&event(DAY - 10, Some(DAY + 2), &[5, 7]));
+        series.add(&event(3 * DAY, None, &[]));
+
+        let rows = series.rows();
+        assert_eq!(rows.len(), 4);
+        assert_eq!((rows[0].events, rows[0].landings), (1, 0));
+        assert_eq!(
+            rows[1],
+            TimeSeriesRow {
+                timestamp: DAY,
+                start: "1970-01-02 00:00".into(),
+                events: 0,
+                landings: 1,
+                refunded: 1,
+                refunds: 2,
+                total_refunded: U256::from(12),
+            }
+        );
+        assert_eq!(rows[2].events + rows[2].landings, 0);
+        assert_eq!(rows[3].events, 1);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    config::{ApiConfig, RetryConfig},
+    error::Result,
+};
+use mev_share::sse::{EventHistory, EventHistoryInfo, EventHistoryParams};
+use rand::Rng;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Client of the MEV-Share history API that retries failed requests with exponential backoff.
+#[derive(Clone)]
+pub struct HistoryClient {
+    client: reqwest::Client,
+    history_url: String,
+    info_url: String,
+    retry: RetryConfig,
+    stats: Arc<RetryStats>,
+}
+
+/// Kind of a failed API request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// HTTP 429.
+    RateLimited,
+    /// HTTP 5xx.
+    Server,
+    /// Any other HTTP error status. Not retried.
+    Client,
+    /// The response body was not the expected JSON.
+    Decode,
+    /// Connection errors and timeouts.
+    Network,
+}
+
+impl Failure {
+    pub fn of(error: &reqwest::Error) -> Self {
+        match error.status() {
+            Some(status) => Self::from_status(status),
+            None if error.is_decode() => Failure::Decode,
+            None => Failure::Network,
+        }
+    }
+
+    fn from_status(status: StatusCode) -> Self {
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            Failure::RateLimited
+        } else if status.is_server_error() {
+            Failure::Server
+        } else {
+            Failure::Client
+        }
+    }
+
+    pub fn is_retryable(self) -> bool {
+        self != Failure::Client
+    }
+}
+
+/// Counters of failed API requests, shared by all clones of a [`HistoryClient`].
+#[derive(Debug, Default)]
+pub struct RetryStats {
+    pub rate_limited: AtomicU64,
+    pub server_errors: AtomicU64,
+    pub client_errors: AtomicU64,
+    pub decode_errors: AtomicU64,
+    pub network_errors: AtomicU64,
+    /// Requests sent again after a failure.
+    pub retries: AtomicU64,
+}
+
+impl RetryStats {
+    fn record(&self, failure: Failure) {
+        let counter = match failure {
+            Failure::RateLimited => &self.rate_limited,
+            Failure::Server => &self.server_errors,
+            Failure::Client => &self.client_errors,
+            Failure::Decode => &self.decode_errors,
+            Failure::Network => &self.network_errors,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Total number of failed requests.
+    pub fn failures(&self) -> u64 {
+        [
+            &self.rate_limited,
+            &self.server_errors,
+            &self.client_errors,
+            &self.decode_errors,
+            &self.network_errors,
+        ]
+        .iter()
+        .map(|counter| counter.load(Ordering::Relaxed))
+        .sum()
+    }
+}
+
+impl std::fmt::Display for RetryStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        write!(
+            f,
+            "{} rate limited, {} server errors, {} client errors, {} decode errors, {} network errors, {} retries",
+            load(&self.rate_limited),
+            load(&self.server_errors),
+            load(&self.client_errors),
+            load(&self.decode_errors),
+            load(&self.network_errors),
+            load(&self.retries),
+        )
+    }
+}
+
+impl HistoryClient {
+    pub fn new(config: &ApiConfig) -> Self {
+        HistoryClient {
+            client: reqwest::Client::new(),
+            history_url: config.history.clone(),
+            info_url: config.history_info.clone(),
+            retry: config.retry.clone(),
+            stats: Arc::new(RetryStats::default()),
+        }
+    }
+
+    pub fn stats(&self) -> &RetryStats {
+        &self.stats
+    }
+
+    pub async fn event_history(&self, params: &EventHistoryParams) -> Result<Vec<EventHistory>> {
+        self.with_retry("history", || {
+            self.get_json(self.client.get(&self.history_url).query(params))
+        })
+        .await
+    }
+
+    pub async fn event_history_info(&self) -> Result<EventHistoryInfo> {
+        self.with_retry("history info", || {
+            self.get_json(self.client.get(&self.info_url))
+        })
+        .await
+    }
+
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> reqwest::Result<T> {
+        request.send().await?.error_for_status()?.json().await
+    }
+
+    /// Runs `request` until it succeeds, fails with a non-retryable error, or runs out of attempts.
+    async fn with_retry<T, F, Fut>(&self, name: &str, mut request: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = reqwest::Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let error = match request().await {
+                Ok(response) => return Ok(response),
+                Err(error) => error,
+            };
+            let failure = Failure::of(&error);
+            self.stats.record(failure);
+            if !failure.is_retryable() || attempt >= self.retry.max_attempts {
+                return Err(error.into());
+            }
+            let delay = backoff(&self.retry, attempt);
+            println!(
+                "{} request failed ({:?}), retrying in {:?} (attempt {}/{}): {}",
+                name, failure, delay, attempt, self.retry.max_attempts, error
+            );
+            tokio::time::sleep(delay).await;
+            self.stats.retries.fetch_add(1, Ordering::Relaxed);
+            attempt += 1;
+        }
+    }
+}
+
+/// Delay before retrying a request that failed for the `attempt`th time.
+fn backoff(config: &RetryConfig, attempt: u32) -> Duration {
+    let exponential = config
+        .initial_backoff_ms
+        .saturating_mul(1_u64 << (attempt - 1).min(32));
+    let ceiling = exponential.min(config.max_backoff_ms);
+    let jittered = rand::thread_rng().gen_range(ceiling / 2..=ceiling);
+    Duration::from_millis(jittered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let config = RetryConfig {
+            max_attempts: 10,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1_000,
+        };
+        for (attempt, ceiling) in [(1, 100), (2, 200), (4, 800), (5, 1_000), (40, 1_000)] {
+            let delay = backoff(&config, attempt).as_millis() as u64;
+            assert!(
+                ceiling / 2 <= delay && delay <= ceiling,
+                "{} {}",
+                attempt,
+                delay
+            );
+        }
+    }
+
+    #[test]
+    fn test_failure_from_status() {
+        assert_eq!(
+            Failure::from_status(StatusCode::TOO_MANY_REQUESTS),
+            Failure::RateLimited
+        );
+        assert_eq!(
+            Failure::from_status(StatusCode::BAD_GATEWAY),
+            Failure::Server
+        );
+        assert_eq!(Failure::from_status(StatusCode::NOT_FOUND), Failure::Client);
+        assert!(!Failure::Client.is_retryable());
+        assert!(Failure::Decode.is_retryable());
+    }
+}
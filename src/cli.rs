@@ -1,16 +1,86 @@
-use clap::{Parser, Subcommand};
+use crate::{
+    analysis::{builders::LeaderboardSort, report::ReportFormat, timeseries::Interval},
+    config::Config,
+    data::store::EventFilter,
+    error::Result,
+};
+use clap::{value_parser, Args, Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Commands>,
+    /// Read settings from this TOML file instead of `mev-share-analysis.toml`.
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// Use the SQLite database at this path instead of MongoDB.
+    #[arg(long, global = true)]
+    pub sqlite: Option<PathBuf>,
+    #[arg(long = "rpc-url", global = true)]
+    pub rpc_url: Option<String>,
+    /// Connect to the node over IPC at this path instead of `rpc-url`.
+    #[arg(long = "ipc-path", global = true)]
+    pub ipc_path: Option<PathBuf>,
+    #[arg(long = "mongo-url", global = true)]
+    pub mongo_url: Option<String>,
+    #[arg(long = "db-name", global = true)]
+    pub db_name: Option<String>,
 }
 
 impl Cli {
     pub fn parse_args() -> Self {
         Self::parse()
     }
+
+    /// Loads the config and applies the overrides given on the command line.
+    pub fn config(&self) -> Result<Config> {
+        let mut config = Config::load(self.config.as_deref())?;
+        if let Some(sqlite) = &self.sqlite {
+            config.store.sqlite = Some(sqlite.clone());
+        }
+        if let Some(rpc_url) = &self.rpc_url {
+            config.rpc.url = rpc_url.clone();
+        }
+        if let Some(ipc_path) = &self.ipc_path {
+            config.rpc.ipc_path = Some(ipc_path.clone());
+        }
+        if let Some(mongo_url) = &self.mongo_url {
+            config.store.mongo_url = mongo_url.clone();
+        }
+        if let Some(db_name) = &self.db_name {
+            config.store.db_name = db_name.clone();
+        }
+        match &self.command {
+            Some(Commands::Events {
+                workers,
+                chunk_size,
+                page_size,
+                ..
+            }) => {
+                config.history.workers = workers.unwrap_or(config.history.workers);
+                config.history.chunk_size = chunk_size.unwrap_or(config.history.chunk_size);
+                config.history.page_size = page_size.or(config.history.page_size);
+            }
+            Some(Commands::ScanRefunds {
+                workers,
+                refund_addresses_file,
+                builders_file,
+                ..
+            }) => {
+                config.scan.workers = workers.or(config.scan.workers);
+                if let Some(path) = refund_addresses_file {
+                    config.scan.refund_addresses_file = Some(path.clone());
+                }
+                if let Some(path) = builders_file {
+                    config.scan.builders_file = Some(path.clone());
+                }
+            }
+            _ => {}
+        }
+        Ok(config)
+    }
 }
 
 #[derive(Subcommand)]
@@ -21,7 +91,136 @@ pub enum Commands {
         block_start: Option<u64>,
         #[arg(long = "block-end")]
         block_end: Option<u64>,
+        /// Ignore the persisted sync cursor and fetch the range from the start.
+        #[arg(long = "from-scratch")]
+        from_scratch: bool,
+        /// Once history is synced, follow the live SSE event stream instead of polling history.
+        #[arg(long, conflicts_with = "block_end")]
+        follow: bool,
+        /// Number of block chunks to fetch concurrently (default: 1). With more than one worker, the
+        /// range up to the latest indexed block is backfilled in parallel before syncing new events.
+        #[arg(long)]
+        workers: Option<usize>,
+        /// Number of blocks per chunk when backfilling with multiple workers (default: 50000).
+        #[arg(long = "chunk-size", value_parser = value_parser!(u64).range(1..))]
+        chunk_size: Option<u64>,
+        /// Number of events per history request (default: the API's max limit).
+        #[arg(long = "page-size")]
+        page_size: Option<u64>,
+    },
+    /// Scan existing events in db for landings and refunds onchain.
+    ScanRefunds {
+        #[command(flatten)]
+        range: RangeArgs,
+        /// Rescan events that already have a final scan status.
+        #[arg(long)]
+        rescan: bool,
+        /// Walk the blocks of the range and scan the stored events landed in each block, instead of
+        /// looking up every event's transaction.
+        #[arg(long = "by-block")]
+        by_block: bool,
+        /// Number of concurrent scanning tasks (default: number of available cores).
+        #[arg(long)]
+        workers: Option<usize>,
+        /// File of refund addresses of senders, one whitespace-separated sender and refund address
+        /// pair per line, in addition to `scan.refund_addresses`.
+        #[arg(long = "refund-addresses-file")]
+        refund_addresses_file: Option<PathBuf>,
+        /// JSON file of known builders, replacing the built-in list.
+        #[arg(long = "builders-file")]
+        builders_file: Option<PathBuf>,
+    },
+    /// Classify the hint disclosure profile of events in db stored without one.
+    Classify {
+        #[command(flatten)]
+        range: RangeArgs,
+        /// Classify every event again, not only the ones without a profile.
+        #[arg(long)]
+        reclassify: bool,
+    },
+    /// Print totals of the scan results stored in db, without scanning.
+    Stats {
+        #[command(flatten)]
+        range: RangeArgs,
+        /// Only count events landed by this builder, given as a fee recipient address or a name.
+        #[arg(long)]
+        builder: Option<String>,
+        #[arg(long, value_enum, default_value_t)]
+        format: ReportFormat,
+    },
+    /// Aggregate scan results of events in db into reports.
+    Report {
+        #[command(subcommand)]
+        report: ReportCommand,
+    },
+    /// Export events in db to Parquet files partitioned by block range.
+    Export {
+        #[arg(long = "block-start")]
+        block_start: Option<u64>,
+        #[arg(long = "block-end")]
+        block_end: Option<u64>,
+        /// Directory to write the Parquet files to.
+        #[arg(long = "out-dir", default_value = "export")]
+        out_dir: PathBuf,
+        /// Number of blocks per partition.
+        #[arg(long = "partition-size", default_value_t = 100_000, value_parser = value_parser!(u64).range(1..))]
+        partition_size: u64,
+        /// Number of rows per table written at once, bounding memory use.
+        #[arg(long = "batch-size", default_value_t = 100_000, value_parser = value_parser!(u64).range(1..))]
+        batch_size: u64,
     },
-    /// Scan all existing events in db for landings and refunds onchain.
-    ScanRefunds,
+}
+
+#[derive(Subcommand)]
+pub enum ReportCommand {
+    /// Landing and refund rates and refund values per hint disclosure profile.
+    Hints {
+        #[command(flatten)]
+        range: RangeArgs,
+        #[arg(long, value_enum, default_value_t)]
+        format: ReportFormat,
+    },
+    /// Builders ranked by MEV-Share transactions included and refunds paid.
+    Builders {
+        #[command(flatten)]
+        range: RangeArgs,
+        #[arg(long = "sort-by", value_enum, default_value_t)]
+        sort_by: LeaderboardSort,
+        #[arg(long, value_enum, default_value_t)]
+        format: ReportFormat,
+    },
+    /// Events, landings and refunds per hour, day or week.
+    Timeseries {
+        #[command(flatten)]
+        range: RangeArgs,
+        #[arg(long, value_enum, default_value_t)]
+        interval: Interval,
+        #[arg(long, value_enum, default_value_t)]
+        format: ReportFormat,
+    },
+}
+
+/// Block and timestamp bounds of the stored events a command reads.
+#[derive(Args, Debug, Clone, Default)]
+pub struct RangeArgs {
+    #[arg(long = "block-start")]
+    pub block_start: Option<u64>,
+    #[arg(long = "block-end")]
+    pub block_end: Option<u64>,
+    #[arg(long = "timestamp-start")]
+    pub timestamp_start: Option<u64>,
+    #[arg(long = "timestamp-end")]
+    pub timestamp_end: Option<u64>,
+}
+
+impl From<RangeArgs> for EventFilter {
+    fn from(range: RangeArgs) -> Self {
+        EventFilter {
+            block_start: range.block_start,
+            block_end: range.block_end,
+            timestamp_start: range.timestamp_start,
+            timestamp_end: range.timestamp_end,
+            ..Default::default()
+        }
+    }
 }
@@ -0,0 +1,268 @@
+use crate::error::{Error, Result};
+use ethers::types::Address;
+use serde::{
+    de::{Error as _, Unexpected},
+    Deserialize, Deserializer,
+};
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Config file read when no `--config` path is given, if it exists.
+pub const DEFAULT_CONFIG_PATH: &str = "mev-share-analysis.toml";
+
+/// Runtime configuration, read from a TOML file and overridden by environment variables and
+/// CLI arguments (in that order of precedence, lowest first).
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub api: ApiConfig,
+    pub rpc: RpcConfig,
+    pub store: StoreConfig,
+    pub history: HistoryConfig,
+    pub scan: ScanConfig,
+}
+
+/// MEV-Share API endpoints.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    pub history: String,
+    pub history_info: String,
+    pub stream: String,
+    pub retry: RetryConfig,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        ApiConfig {
+            history: "https://mev-share.flashbots.net/api/v1/history".into(),
+            history_info: "https://mev-share.flashbots.net/api/v1/history/info".into(),
+            stream: "https://mev-share.flashbots.net".into(),
+            retry: RetryConfig::default(),
+        }
+    }
+}
+
+/// Retry policy of history API requests. The delay before retry `n` is drawn between half and
+/// all of `initial_backoff_ms * 2^(n - 1)`, capped at `max_backoff_ms`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    /// Number of attempts of a request before giving up, including the first one.
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_attempts: 8,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+        }
+    }
+}
+
+/// Ethereum node connection. `ipc_path` takes precedence over `url` when set.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RpcConfig {
+    pub url: String,
+    pub ipc_path: Option<PathBuf>,
+    /// Number of transaction lookups per JSON-RPC batch request. Batches are only sent over HTTP.
+    pub batch_size: usize,
+    /// Number of full blocks kept in memory while scanning.
+    pub block_cache_size: usize,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        RpcConfig {
+            url: "http://localhost:8545".into(),
+            ipc_path: None,
+            batch_size: 100,
+            block_cache_size: 256,
+        }
+    }
+}
+
+/// Event store connection. `sqlite` takes precedence over MongoDB when set.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct StoreConfig {
+    pub mongo_url: String,
+    pub db_name: String,
+    pub events_collection: String,
+    pub sync_cursors_collection: String,
+    pub sqlite: Option<PathBuf>,
+    /// Number of events per MongoDB cursor batch when reading events.
+    pub read_batch_size: u32,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        StoreConfig {
+            mongo_url: "mongodb://localhost:27017".into(),
+            db_name: "mev-share-test".into(),
+            events_collection: "events".into(),
+            sync_cursors_collection: "sync_cursors".into(),
+            sqlite: None,
+            read_batch_size: 10_000_000, // 10 million
+        }
+    }
+}
+
+/// History sync settings of the `events` command.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    /// Number of block chunks fetched concurrently.
+    pub workers: usize,
+    /// Number of blocks per chunk when `workers` is more than one.
+    #[serde(deserialize_with = "positive")]
+    pub chunk_size: u64,
+    /// Number of events per history request. Defaults to the API's `maxLimit`, which also caps it.
+    pub page_size: Option<u64>,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            workers: 1,
+            chunk_size: 50_000,
+            page_size: None,
+        }
+    }
+}
+
+fn positive<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match u64::deserialize(deserializer)? {
+        0 => Err(D::Error::invalid_value(
+            Unexpected::Unsigned(0),
+            &"a positive number",
+        )),
+        value => Ok(value),
+    }
+}
+
+/// Settings of the `scan-refunds` command.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScanConfig {
+    /// Number of concurrent scanning tasks. Defaults to the number of available cores.
+    pub workers: Option<usize>,
+    /// Refund addresses of each sender, accepted as refund recipients of its txs besides the
+    /// sender itself.
+    pub refund_addresses: HashMap<Address, Vec<Address>>,
+    /// File of more refund addresses, one sender and refund address pair per line.
+    pub refund_addresses_file: Option<PathBuf>,
+    /// JSON file of known builders, replacing the built-in list. See [`BuilderRegistry::load`].
+    ///
+    /// [`BuilderRegistry::load`]: crate::refunds::builder::BuilderRegistry::load
+    pub builders_file: Option<PathBuf>,
+}
+
+impl Config {
+    /// Reads the config file at `path`, or [`DEFAULT_CONFIG_PATH`] if it exists, and applies
+    /// environment overrides.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let default_path = Path::new(DEFAULT_CONFIG_PATH);
+        let path = path.or(default_path.exists().then_some(default_path));
+        let mut config = match path {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path)?;
+                Self::parse(&contents)
+                    .map_err(|error| Error::decode(path.display().to_string(), error))?
+            }
+            None => Config::default(),
+        };
+        config.apply_env();
+        Ok(config)
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(contents)
+    }
+
+    /// Overrides settings from environment variables (a `.env` file is loaded at startup).
+    fn apply_env(&mut self) {
+        let var = |name: &str| env::var(name).ok();
+        if let Some(url) = var("HISTORY_URL") {
+            self.api.history = url;
+        }
+        if let Some(url) = var("HISTORY_INFO_URL") {
+            self.api.history_info = url;
+        }
+        if let Some(url) = var("STREAM_URL") {
+            self.api.stream = url;
+        }
+        if let Some(url) = var("RPC_URL") {
+            self.rpc.url = url;
+        }
+        if let Some(path) = var("IPC_PATH") {
+            self.rpc.ipc_path = Some(path.into());
+        }
+        if let Some(url) = var("MONGO_URL") {
+            self.store.mongo_url = url;
+        }
+        if let Some(name) = var("DB_NAME") {
+            self.store.db_name = name;
+        }
+        if let Some(name) = var("EVENTS_COLLECTION") {
+            self.store.events_collection = name;
+        }
+        if let Some(path) = var("SQLITE_PATH") {
+            self.store.sqlite = Some(path.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_partial_config() {
+        let config = Config::parse(
+            r#"
+            [api]
+            history = "http://localhost:3000/api/v1/history"
+
+            [api.retry]
+            max_attempts = 3
+
+            [store]
+            db_name = "mev-share-staging"
+
+            [history]
+            workers = 8
+
+            [scan.refund_addresses]
+            "0x00000000000000000000000000000000000000aa" = ["0x00000000000000000000000000000000000000bb"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.api.history, "http://localhost:3000/api/v1/history");
+        assert_eq!(config.api.history_info, ApiConfig::default().history_info);
+        assert_eq!(config.api.retry.max_attempts, 3);
+        assert_eq!(config.api.retry.max_backoff_ms, 30_000);
+        assert_eq!(config.store.db_name, "mev-share-staging");
+        assert_eq!(config.store.events_collection, "events");
+        assert_eq!(config.history.workers, 8);
+        assert_eq!(config.history.chunk_size, 50_000);
+        assert_eq!(
+            config.scan.refund_addresses,
+            HashMap::from([(
+                Address::from_low_u64_be(0xaa),
+                vec![Address::from_low_u64_be(0xbb)]
+            )])
+        );
+
+        assert!(Config::parse("[store]\nmongo = \"typo\"").is_err());
+        assert!(Config::parse("[history]\nchunk_size = 0").is_err());
+    }
+}
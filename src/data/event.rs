@@ -1,26 +1,149 @@
+use super::profile::DisclosureProfile;
 use crate::refunds::{landing::Landing, refund::Refund};
-use mev_share::sse::{EventHistory, Hint};
-use serde::{Deserialize, Serialize};
+use ethers::types::U256;
+use mev_share::sse::{self, EventHistory, Hint};
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Event {
     pub block: u64,
     pub timestamp: u64,
     pub hint: Hint,
-    pub refund: Option<Refund>,
+    /// Refunds of the landed transaction, in block order. Events stored before multiple refunds
+    /// were recorded have a single `refund` field instead, which is read into this list.
+    #[serde(default, alias = "refund", deserialize_with = "one_or_many")]
+    pub refunds: Vec<Refund>,
     pub landing: Option<Landing>,
     pub landed: Option<bool>,
+    pub scan_status: Option<ScanStatus>,
+    /// Unix time in milliseconds at which the event was received from the live SSE stream.
+    /// `None` for events fetched from the history endpoint.
+    pub received_at: Option<u64>,
+    /// Hint fields the event revealed. `None` for events stored before profiles were classified,
+    /// and for events received from the live stream, whose hints never include `mev_gas_price`
+    /// and `gas_used`.
+    #[serde(default)]
+    pub disclosure_profile: Option<DisclosureProfile>,
+}
+
+/// Number of blocks after the hint within which a MEV-Share transaction can still land.
+/// Events found not landed before this window closed are rescanned.
+pub const LANDING_WINDOW: u64 = 25;
+
+/// Outcome of the last landing and refund scan of an event.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ScanStatus {
+    /// The transaction had not landed as of block `as_of_block`.
+    NotLanded { as_of_block: u64 },
+    /// The transaction landed without a refund.
+    Landed,
+    /// The transaction landed and triggered a refund.
+    Refunded,
+    /// The scan failed and should be retried.
+    Error { message: String },
+}
+
+impl ScanStatus {
+    /// Name of the status, as stored in the `status` tag.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScanStatus::NotLanded { .. } => "not_landed",
+            ScanStatus::Landed => "landed",
+            ScanStatus::Refunded => "refunded",
+            ScanStatus::Error { .. } => "error",
+        }
+    }
 }
 
 impl Event {
     pub fn new(event: EventHistory) -> Event {
         Event {
+            disclosure_profile: Some(DisclosureProfile::classify(&event.hint)),
             block: event.block,
             timestamp: event.timestamp,
             hint: event.hint,
-            refund: None,
+            refunds: vec![],
             landing: None,
             landed: None,
+            scan_status: None,
+            received_at: None,
         }
     }
+
+    /// Creates an event from a hint received on the live SSE stream at `received_at` (unix millis),
+    /// while `block` was the latest block. The event has no disclosure profile, as the stream
+    /// omits the gas fields of the hint.
+    pub fn from_stream(event: sse::Event, block: u64, received_at: u64) -> Event {
+        let hint = Hint {
+            txs: event.transactions,
+            hash: event.hash,
+            logs: event.logs,
+            gas_used: None,
+            mev_gas_price: None,
+        };
+        Event {
+            block,
+            timestamp: received_at / 1000,
+            hint,
+            refunds: vec![],
+            landing: None,
+            landed: None,
+            scan_status: None,
+            received_at: Some(received_at),
+            disclosure_profile: None,
+        }
+    }
+
+    /// Total refunded wei, saturating at [`U256::MAX`].
+    pub fn refunded(&self) -> U256 {
+        self.refunds.iter().fold(U256::zero(), |total, refund| {
+            total.saturating_add(refund.value)
+        })
+    }
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Refund>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        Many(Vec<Refund>),
+        One(Option<Refund>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::Many(refunds) => refunds,
+        OneOrMany::One(refund) => refund.into_iter().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_legacy_refund() {
+        let event = Event::new(EventHistory {
+            block: 1,
+            timestamp: 2,
+            hint: Hint {
+                hash: Default::default(),
+                txs: vec![],
+                logs: vec![],
+                mev_gas_price: None,
+                gas_used: None,
+            },
+        });
+        let mut json = serde_json::to_value(&event).unwrap();
+        let legacy = json.as_object_mut().unwrap();
+        legacy.remove("refunds");
+        legacy.insert("refund".into(), serde_json::json!({"value": 1000}));
+        let read: Event = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(read.refunds.len(), 1);
+        assert_eq!(read.refunded(), U256::from(1_000));
+
+        json["refund"] = serde_json::Value::Null;
+        let read: Event = serde_json::from_value(json).unwrap();
+        assert_eq!(read, event);
+    }
 }
@@ -0,0 +1,495 @@
+use super::{
+    event::Event,
+    store::{EventFilter, EventStore},
+};
+use crate::error::Result;
+use arrow_array::{
+    builder::{ArrayBuilder, BooleanBuilder, StringBuilder, UInt32Builder, UInt64Builder},
+    ArrayRef, RecordBatch,
+};
+use arrow_schema::{Field, Schema};
+use futures::StreamExt;
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Block of the first MEV-Share event.
+pub const MEV_SHARE_START_BLOCK: u64 = 17422191;
+
+/// Number of events written by [`export_parquet`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    pub partitions: u64,
+    pub events: u64,
+    pub txs: u64,
+    pub logs: u64,
+    pub refunds: u64,
+    pub backruns: u64,
+}
+
+/// Exports the events in `block_start..=block_end` to Parquet files in `out_dir`.
+///
+/// Each partition of `partition_size` blocks is written as five files:
+/// `events_<start>_<end>.parquet` with one row per event, and `hint_txs_<start>_<end>.parquet` /
+/// `hint_logs_<start>_<end>.parquet` / `refunds_<start>_<end>.parquet` /
+/// `backruns_<start>_<end>.parquet` with one row per hint tx / log / refund / backrun, joined to
+/// events on `hash`.
+/// Rows are written in record batches of at most `batch_size` rows per table, so a partition is
+/// never held in memory as a whole.
+/// Without `block_end`, partitions are written until no stored events remain.
+pub async fn export_parquet<S: EventStore>(
+    store: &S,
+    out_dir: &Path,
+    block_start: Option<u64>,
+    block_end: Option<u64>,
+    partition_size: u64,
+    batch_size: usize,
+) -> Result<ExportSummary> {
+    std::fs::create_dir_all(out_dir)?;
+
+    let mut summary = ExportSummary::default();
+    let mut start = block_start.unwrap_or(MEV_SHARE_START_BLOCK);
+    while block_end.is_none_or(|block_end| start <= block_end) {
+        let remaining = EventFilter {
+            block_start: Some(start),
+            block_end,
+            ..Default::default()
+        };
+        if store.count(&remaining).await? == 0 {
+            break;
+        }
+
+        let mut end = start + partition_size - 1;
+        if let Some(block_end) = block_end {
+            end = end.min(block_end);
+        }
+        let filter = EventFilter {
+            block_start: Some(start),
+            block_end: Some(end),
+            ..Default::default()
+        };
+        let suffix = format!("{}_{}", start, end);
+        let mut partition = Partition::new(out_dir, &suffix, batch_size);
+        let mut events = store.read_events(&filter).await?;
+        while let Some(event) = events.next().await {
+            partition.push(&event?)?;
+        }
+        if partition.events > 0 {
+            summary.events += partition.events;
+            summary.txs += partition.txs;
+            summary.logs += partition.logs;
+            summary.refunds += partition.refunds;
+            summary.backruns += partition.backruns;
+            summary.partitions += 1;
+            partition.close()?;
+            println!("Exported blocks {} to {}", start, end);
+        }
+        start = end + 1;
+    }
+    Ok(summary)
+}
+
+/// Column builders and files for the five tables of one block-range partition.
+///
+/// Builders of a table are written to its file as a record batch once they hold `batch_size`
+/// rows.
+#[derive(Default)]
+struct Partition {
+    batch_size: usize,
+    events_file: TableFile,
+    txs_file: TableFile,
+    logs_file: TableFile,
+    refunds_file: TableFile,
+    backruns_file: TableFile,
+
+    events: u64,
+    txs: u64,
+    logs: u64,
+    refunds: u64,
+    backruns: u64,
+
+    // events
+    hash: StringBuilder,
+    block: UInt64Builder,
+    timestamp: UInt64Builder,
+    gas_used: StringBuilder,
+    mev_gas_price: StringBuilder,
+    tx_count: UInt32Builder,
+    log_count: UInt32Builder,
+    landed: BooleanBuilder,
+    landing_block: UInt64Builder,
+    landing_timestamp: UInt64Builder,
+    builder: StringBuilder,
+    builder_name: StringBuilder,
+    proposer_payment: StringBuilder,
+    landing_success: BooleanBuilder,
+    landing_gas_used: UInt64Builder,
+    landing_effective_gas_price: StringBuilder,
+    landing_tx_index: UInt64Builder,
+    refund_count: UInt32Builder,
+    refund_value: StringBuilder,
+    scan_status: StringBuilder,
+    received_at: UInt64Builder,
+    disclosure_profile: StringBuilder,
+
+    // hint_txs
+    tx_hash: StringBuilder,
+    tx_index: UInt32Builder,
+    tx_to: StringBuilder,
+    tx_function_selector: StringBuilder,
+    tx_calldata: StringBuilder,
+
+    // hint_logs
+    log_hash: StringBuilder,
+    log_index: UInt32Builder,
+    log_address: StringBuilder,
+    log_topic0: StringBuilder,
+    log_topic1: StringBuilder,
+    log_topic2: StringBuilder,
+    log_topic3: StringBuilder,
+    log_data: StringBuilder,
+
+    // refunds
+    refund_hash: StringBuilder,
+    refund_index: UInt32Builder,
+    refund_tx: StringBuilder,
+    refund_tx_value: StringBuilder,
+    refund_recipient: StringBuilder,
+    refund_rule: StringBuilder,
+
+    // backruns
+    backrun_hash: StringBuilder,
+    backrun_index: UInt32Builder,
+    backrun_tx: StringBuilder,
+    backrun_from: StringBuilder,
+    backrun_gas_used: UInt64Builder,
+    backrun_coinbase_payment: StringBuilder,
+}
+
+impl Partition {
+    fn new(out_dir: &Path, suffix: &str, batch_size: usize) -> Self {
+        let file = |table: &str| TableFile {
+            path: out_dir.join(format!("{}_{}.parquet", table, suffix)),
+            batch_size,
+            writer: None,
+        };
+        Self {
+            batch_size,
+            events_file: file("events"),
+            txs_file: file("hint_txs"),
+            logs_file: file("hint_logs"),
+            refunds_file: file("refunds"),
+            backruns_file: file("backruns"),
+            ..Default::default()
+        }
+    }
+
+    fn push(&mut self, event: &Event) -> Result<()> {
+        let hash = format!("{:?}", event.hint.hash);
+        self.events += 1;
+        self.hash.append_value(&hash);
+        self.block.append_value(event.block);
+        self.timestamp.append_value(event.timestamp);
+        self.gas_used
+            .append_option(event.hint.gas_used.map(|gas| gas.to_string()));
+        self.mev_gas_price
+            .append_option(event.hint.mev_gas_price.map(|price| price.to_string()));
+        self.tx_count.append_value(event.hint.txs.len() as u32);
+        self.log_count.append_value(event.hint.logs.len() as u32);
+        self.landed.append_option(event.landed);
+        let landing = event.landing.as_ref();
+        self.landing_block
+            .append_option(landing.map(|landing| landing.block));
+        self.landing_timestamp
+            .append_option(landing.map(|landing| landing.timestamp));
+        self.builder
+            .append_option(landing.map(|landing| format!("{:?}", landing.builder)));
+        self.builder_name
+            .append_option(landing.and_then(|landing| landing.builder_name.as_ref()));
+        self.proposer_payment.append_option(
+            landing.and_then(|landing| landing.proposer_payment.map(|hash| format!("{:?}", hash))),
+        );
+        self.landing_success
+            .append_option(landing.and_then(|landing| landing.success));
+        self.landing_gas_used
+            .append_option(landing.and_then(|landing| landing.gas_used));
+        self.landing_effective_gas_price.append_option(
+            landing.and_then(|landing| landing.effective_gas_price.map(|price| price.to_string())),
+        );
+        self.landing_tx_index
+            .append_option(landing.and_then(|landing| landing.tx_index));
+        self.refund_count.append_value(event.refunds.len() as u32);
+        self.refund_value.append_value(event.refunded().to_string());
+        self.scan_status
+            .append_option(event.scan_status.as_ref().map(|status| status.as_str()));
+        self.received_at.append_option(event.received_at);
+        self.disclosure_profile
+            .append_option(event.disclosure_profile.map(|profile| profile.to_string()));
+
+        for (i, tx) in event.hint.txs.iter().enumerate() {
+            self.txs += 1;
+            self.tx_hash.append_value(&hash);
+            self.tx_index.append_value(i as u32);
+            self.tx_to
+                .append_option(tx.to.map(|to| format!("{:?}", to)));
+            self.tx_function_selector
+                .append_option(tx.function_selector.as_ref().map(|s| s.to_string()));
+            self.tx_calldata
+                .append_option(tx.calldata.as_ref().map(|calldata| calldata.to_string()));
+        }
+
+        for (i, log) in event.hint.logs.iter().enumerate() {
+            self.logs += 1;
+            self.log_hash.append_value(&hash);
+            self.log_index.append_value(i as u32);
+            self.log_address.append_value(format!("{:?}", log.address));
+            let topic = |n: usize| log.topics.get(n).map(|topic| format!("{:?}", topic));
+            self.log_topic0.append_option(topic(0));
+            self.log_topic1.append_option(topic(1));
+            self.log_topic2.append_option(topic(2));
+            self.log_topic3.append_option(topic(3));
+            self.log_data.append_value(log.data.to_string());
+        }
+
+        for (i, refund) in event.refunds.iter().enumerate() {
+            self.refunds += 1;
+            self.refund_hash.append_value(&hash);
+            self.refund_index.append_value(i as u32);
+            self.refund_tx
+                .append_value(format!("{:?}", refund.refund_tx));
+            self.refund_tx_value.append_value(refund.value.to_string());
+            self.refund_recipient
+                .append_value(format!("{:?}", refund.recipient));
+            self.refund_rule.append_value(refund.rule.as_str());
+        }
+
+        for (i, backrun) in landing
+            .iter()
+            .flat_map(|landing| &landing.backruns)
+            .enumerate()
+        {
+            self.backruns += 1;
+            self.backrun_hash.append_value(&hash);
+            self.backrun_index.append_value(i as u32);
+            self.backrun_tx.append_value(format!("{:?}", backrun.hash));
+            self.backrun_from
+                .append_value(format!("{:?}", backrun.from));
+            self.backrun_gas_used.append_option(backrun.gas_used);
+            self.backrun_coinbase_payment
+                .append_value(backrun.coinbase_payment.to_string());
+        }
+        self.flush(false)
+    }
+
+    /// Writes the buffered rows of every table holding `batch_size` rows, or of all tables if
+    /// `all` is set.
+    fn flush(&mut self, all: bool) -> Result<()> {
+        if all || self.hash.len() >= self.batch_size {
+            self.events_file.write(vec![
+                ("hash", Arc::new(self.hash.finish()) as ArrayRef),
+                ("block", Arc::new(self.block.finish())),
+                ("timestamp", Arc::new(self.timestamp.finish())),
+                ("gas_used", Arc::new(self.gas_used.finish())),
+                ("mev_gas_price", Arc::new(self.mev_gas_price.finish())),
+                ("tx_count", Arc::new(self.tx_count.finish())),
+                ("log_count", Arc::new(self.log_count.finish())),
+                ("landed", Arc::new(self.landed.finish())),
+                ("landing_block", Arc::new(self.landing_block.finish())),
+                (
+                    "landing_timestamp",
+                    Arc::new(self.landing_timestamp.finish()),
+                ),
+                ("builder", Arc::new(self.builder.finish())),
+                ("builder_name", Arc::new(self.builder_name.finish())),
+                ("proposer_payment", Arc::new(self.proposer_payment.finish())),
+                ("landing_success", Arc::new(self.landing_success.finish())),
+                ("landing_gas_used", Arc::new(self.landing_gas_used.finish())),
+                (
+                    "landing_effective_gas_price",
+                    Arc::new(self.landing_effective_gas_price.finish()),
+                ),
+                ("landing_tx_index", Arc::new(self.landing_tx_index.finish())),
+                ("refund_count", Arc::new(self.refund_count.finish())),
+                ("refund_value", Arc::new(self.refund_value.finish())),
+                ("scan_status", Arc::new(self.scan_status.finish())),
+                ("received_at", Arc::new(self.received_at.finish())),
+                (
+                    "disclosure_profile",
+                    Arc::new(self.disclosure_profile.finish()),
+                ),
+            ])?;
+        }
+        if all || self.tx_hash.len() >= self.batch_size {
+            self.txs_file.write(vec![
+                ("hash", Arc::new(self.tx_hash.finish()) as ArrayRef),
+                ("tx_index", Arc::new(self.tx_index.finish())),
+                ("to", Arc::new(self.tx_to.finish())),
+                (
+                    "function_selector",
+                    Arc::new(self.tx_function_selector.finish()),
+                ),
+                ("calldata", Arc::new(self.tx_calldata.finish())),
+            ])?;
+        }
+        if all || self.log_hash.len() >= self.batch_size {
+            self.logs_file.write(vec![
+                ("hash", Arc::new(self.log_hash.finish()) as ArrayRef),
+                ("log_index", Arc::new(self.log_index.finish())),
+                ("address", Arc::new(self.log_address.finish())),
+                ("topic0", Arc::new(self.log_topic0.finish())),
+                ("topic1", Arc::new(self.log_topic1.finish())),
+                ("topic2", Arc::new(self.log_topic2.finish())),
+                ("topic3", Arc::new(self.log_topic3.finish())),
+                ("data", Arc::new(self.log_data.finish())),
+            ])?;
+        }
+        if all || self.refund_hash.len() >= self.batch_size {
+            self.refunds_file.write(vec![
+                ("hash", Arc::new(self.refund_hash.finish()) as ArrayRef),
+                ("refund_index", Arc::new(self.refund_index.finish())),
+                ("refund_tx", Arc::new(self.refund_tx.finish())),
+                ("value", Arc::new(self.refund_tx_value.finish())),
+                ("recipient", Arc::new(self.refund_recipient.finish())),
+                ("rule", Arc::new(self.refund_rule.finish())),
+            ])?;
+        }
+        if all || self.backrun_hash.len() >= self.batch_size {
+            self.backruns_file.write(vec![
+                ("hash", Arc::new(self.backrun_hash.finish()) as ArrayRef),
+                ("backrun_index", Arc::new(self.backrun_index.finish())),
+                ("backrun_tx", Arc::new(self.backrun_tx.finish())),
+                ("from", Arc::new(self.backrun_from.finish())),
+                ("gas_used", Arc::new(self.backrun_gas_used.finish())),
+                (
+                    "coinbase_payment",
+                    Arc::new(self.backrun_coinbase_payment.finish()),
+                ),
+            ])?;
+        }
+        Ok(())
+    }
+
+    fn close(mut self) -> Result<()> {
+        self.flush(true)?;
+        self.events_file.close()?;
+        self.txs_file.close()?;
+        self.logs_file.close()?;
+        self.refunds_file.close()?;
+        self.backruns_file.close()
+    }
+}
+
+/// Parquet file of one partition table, created with the schema of its first batch.
+#[derive(Default)]
+struct TableFile {
+    path: PathBuf,
+    batch_size: usize,
+    writer: Option<ArrowWriter<File>>,
+}
+
+impl TableFile {
+    fn write(&mut self, columns: Vec<(&str, ArrayRef)>) -> Result<()> {
+        let fields: Vec<Field> = columns
+            .iter()
+            .map(|(name, column)| Field::new(*name, column.data_type().clone(), true))
+            .collect();
+        let schema = Arc::new(Schema::new(fields));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            columns.into_iter().map(|(_, column)| column).collect(),
+        )?;
+        // An empty batch is only written to create the file of a table without rows.
+        if batch.num_rows() == 0 && self.writer.is_some() {
+            return Ok(());
+        }
+
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            None => {
+                let file = File::create(&self.path)?;
+                let props = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .set_max_row_group_size(self.batch_size)
+                    .build();
+                self.writer
+                    .insert(ArrowWriter::try_new(file, schema, Some(props))?)
+            }
+        };
+        writer.write(&batch)?;
+        Ok(())
+    }
+
+    fn close(self) -> Result<()> {
+        if let Some(writer) = self.writer {
+            writer.close()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::sqlite::SqliteStore;
+    use ethers::types::{Bytes, H160, H256};
+    use mev_share::sse::{EventHistory, EventTransactionLog, Hint};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    /// Returns the number of rows and row groups of a Parquet file.
+    fn num_rows(path: &Path) -> (i64, usize) {
+        let reader = SerializedFileReader::new(File::open(path).unwrap()).unwrap();
+        let metadata = reader.metadata();
+        (
+            metadata.file_metadata().num_rows(),
+            metadata.num_row_groups(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_export_parquet() {
+        let store = SqliteStore::in_memory().unwrap();
+        let events = (0..10)
+            .map(|i| {
+                Event::new(EventHistory {
+                    hint: Hint {
+                        hash: H256::random(),
+                        txs: vec![],
+                        logs: vec![EventTransactionLog {
+                            address: H160::random(),
+                            topics: vec![H256::random()],
+                            data: Bytes::default(),
+                        }],
+                        mev_gas_price: None,
+                        gas_used: None,
+                    },
+                    block: 100 + i,
+                    timestamp: i,
+                })
+            })
+            .collect();
+        store.write_events(events).await.unwrap();
+
+        let out_dir = std::env::temp_dir().join(format!("export-{:?}", H256::random()));
+        let summary = export_parquet(&store, &out_dir, Some(100), None, 4, 3)
+            .await
+            .unwrap();
+        assert_eq!(
+            summary,
+            ExportSummary {
+                partitions: 3,
+                events: 10,
+                txs: 0,
+                logs: 10,
+                refunds: 0,
+                backruns: 0,
+            }
+        );
+        assert_eq!(num_rows(&out_dir.join("events_100_103.parquet")), (4, 2));
+        assert_eq!(num_rows(&out_dir.join("hint_logs_108_111.parquet")), (2, 1));
+        assert_eq!(num_rows(&out_dir.join("hint_txs_108_111.parquet")), (0, 0));
+        std::fs::remove_dir_all(out_dir).unwrap();
+    }
+}
@@ -1,2 +1,6 @@
 pub mod event;
+pub mod export;
 pub mod mongo;
+pub mod profile;
+pub mod sqlite;
+pub mod store;
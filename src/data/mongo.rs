@@ -1,102 +1,326 @@
-use super::event::Event;
+use super::{
+    event::{Event, LANDING_WINDOW},
+    store::{EventFilter, EventStore, EventStream, EventUpdate, SyncCursor, WriteSummary},
+};
+use crate::error::{Error, Result};
+use async_trait::async_trait;
 use ethers::types::H256;
+use futures::{StreamExt, TryStreamExt};
 use mongodb::{
-    bson::{doc, Document},
-    options::{ClientOptions, FindOptions, Hint as IndexHint, UpdateOptions},
-    Client, Collection, Cursor,
+    bson::{doc, to_bson, to_document, Bson, Document},
+    options::{
+        AggregateOptions, ClientOptions, FindOneOptions, FindOptions, Hint as IndexHint,
+        IndexOptions, ReplaceOptions, UpdateOptions,
+    },
+    Client, Collection, IndexModel,
 };
 #[derive(Clone)]
 pub struct MongoClient {
     pub client: Client,
     pub collection: Collection<Event>,
+    pub sync_cursors: Collection<SyncCursor>,
+    /// Number of events per cursor batch in `read_events`.
+    pub read_batch_size: u32,
 }
 
 const APP_NAME: &str = "MEV-Share-Analytics";
+const SYNC_CURSORS_COLLECTION: &str = "sync_cursors";
+/// Name MongoDB gives the unique index on `hint.hash` and `block`.
+const NATURAL_KEY_INDEX: &str = "hint.hash_1_block_1";
 
 impl MongoClient {
-    pub async fn new(conn_str: &str, db_name: &str, collection_name: &str) -> Self {
-        let mut client_options = ClientOptions::parse(conn_str).await.unwrap();
+    /// Connects to `conn_str` and creates the indexes of the events collection. Events stored
+    /// more than once under the same hint hash and block are deduplicated before the unique index
+    /// on them is first created.
+    pub async fn new(conn_str: &str, db_name: &str, collection_name: &str) -> Result<Self> {
+        let mut client_options = ClientOptions::parse(conn_str).await?;
         client_options.app_name = Some(APP_NAME.to_string());
-        let client = Client::with_options(client_options).unwrap();
+        let client = Client::with_options(client_options)?;
 
         let collection: Collection<Event> = client.database(db_name).collection(collection_name);
+        let sync_cursors: Collection<SyncCursor> =
+            client.database(db_name).collection(SYNC_CURSORS_COLLECTION);
+        let mut indexes: Vec<IndexModel> = [doc! {"hint.hash": 1}, doc! {"block": 1}]
+            .into_iter()
+            .map(|keys| IndexModel::builder().keys(keys).build())
+            .collect();
+        // Natural key of an event, used to upsert events in `write_events`.
+        indexes.push(
+            IndexModel::builder()
+                .keys(doc! {"hint.hash": 1, "block": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+        );
+        if !collection
+            .list_index_names()
+            .await?
+            .iter()
+            .any(|name| name == NATURAL_KEY_INDEX)
+        {
+            let removed = Self::remove_duplicates(&collection).await?;
+            if removed > 0 {
+                println!("Removed {} duplicate events", removed);
+            }
+        }
+        collection.create_indexes(indexes, None).await?;
 
-        MongoClient { client, collection }
+        Ok(MongoClient {
+            client,
+            collection,
+            sync_cursors,
+            read_batch_size: 10_000_000, // 10 million
+        })
     }
 
-    pub async fn write_event(&self, event: Event) {
-        let res = self
-            .collection
-            .insert_one(event, None)
-            .await
-            .unwrap_or_else(|error| panic!("Failed to insert event: {}", error));
+    /// Deletes the copy of the event with hint hash `hash` received from the live stream, if any,
+    /// and returns its receive time.
+    async fn take_stream_copy(&self, hash: H256) -> Result<Option<u64>> {
+        let mut filter = Self::hash_filter(hash);
+        filter.insert("received_at", doc! {"$ne": null});
+        filter.insert("disclosure_profile", Bson::Null);
+        let copy = self.collection.find_one_and_delete(filter, None).await?;
+        Ok(copy.and_then(|copy| copy.received_at))
+    }
+
+    /// Deletes all but the first stored copy of each event with the same hint hash and block, and
+    /// returns the number of events deleted.
+    async fn remove_duplicates(collection: &Collection<Event>) -> Result<u64> {
+        let pipeline = [
+            doc! {"$group": {
+                "_id": {"hash": "$hint.hash", "block": "$block"},
+                "ids": {"$push": "$_id"},
+            }},
+            doc! {"$match": {"ids.1": {"$exists": true}}},
+        ];
+        let options = AggregateOptions::builder().allow_disk_use(true).build();
+        let mut groups = collection.aggregate(pipeline, options).await?;
+        let mut removed = 0;
+        while let Some(group) = groups.try_next().await? {
+            let ids = group
+                .get_array("ids")
+                .map_err(|error| Error::decode("duplicate events", error))?;
+            let res = collection
+                .delete_many(doc! {"_id": {"$in": &ids[1..]}}, None)
+                .await?;
+            removed += res.deleted_count;
+        }
+        Ok(removed)
+    }
+
+    pub fn with_read_batch_size(mut self, read_batch_size: u32) -> Self {
+        self.read_batch_size = read_batch_size;
+        self
+    }
+
+    /// Stores sync cursors in `collection_name` of the events database.
+    pub fn with_sync_cursors_collection(mut self, collection_name: &str) -> Self {
+        let db_name = &self.collection.namespace().db;
+        self.sync_cursors = self.client.database(db_name).collection(collection_name);
+        self
+    }
+
+    pub async fn write_event(&self, event: Event) -> Result<()> {
+        let res = self.collection.insert_one(event, None).await?;
         println!("Inserted event with _id: {:?}", res.inserted_id);
+        Ok(())
     }
 
-    pub async fn read_events(
-        &self,
-        filter: Option<Document>,
-        find_options: Option<FindOptions>,
-    ) -> Cursor<Event> {
-        self.collection
-            .find(filter, find_options)
-            .await
-            .unwrap_or_else(|error| panic!("Failed to read event: {}", error))
+    pub async fn read_event(&self, hash: H256) -> Result<Option<Event>> {
+        Ok(self
+            .collection
+            .find_one(Self::hash_filter(hash), None)
+            .await?)
     }
 
-    pub async fn read_event(&self, hash: H256) -> Option<Event> {
-        let filter = doc! {"hint.hash": serde_json::to_string(&hash).unwrap().replace('\"', "")}; // TODO: Find better way, super unsexy rust.
-        self.collection
-            .find_one(filter, None)
-            .await
-            .unwrap_or_else(|error| panic!("Failed to read event: {}", error))
+    fn hash_filter(hash: H256) -> Document {
+        doc! {"hint.hash": format!("{:?}", hash)}
     }
 
-    pub async fn count(&self) -> u64 {
-        self.collection
-            .count_documents(None, None)
-            .await
-            .unwrap_or_else(|error| panic!("Failed to count events: {}", error))
+    /// Index to read the events matching `filter` with: the hint hash index when matching hashes,
+    /// the block index when matching a block range, otherwise left to the query planner.
+    fn index_hint(filter: &EventFilter) -> Option<IndexHint> {
+        if filter.hashes.is_some() {
+            Some(IndexHint::Keys(doc! {"hint.hash": 1}))
+        } else if filter.block_start.is_some() || filter.block_end.is_some() {
+            Some(IndexHint::Keys(doc! {"block": 1}))
+        } else {
+            None
+        }
     }
 
-    pub async fn write_events(&self, events: Vec<Event>) -> u64 {
-        let res = self
+    fn filter_document(filter: &EventFilter) -> Document {
+        let mut document = Document::new();
+        let ranges = [
+            ("block", filter.block_start, filter.block_end),
+            ("timestamp", filter.timestamp_start, filter.timestamp_end),
+        ];
+        for (field, start, end) in ranges {
+            let mut range = Document::new();
+            if let Some(start) = start {
+                range.insert("$gte", start as i64);
+            }
+            if let Some(end) = end {
+                range.insert("$lte", end as i64);
+            }
+            if !range.is_empty() {
+                document.insert(field, range);
+            }
+        }
+        if let Some(hashes) = &filter.hashes {
+            let hashes: Vec<String> = hashes.iter().map(|hash| format!("{:?}", hash)).collect();
+            document.insert("hint.hash", doc! {"$in": hashes});
+        }
+        if filter.unclassified {
+            document.insert("disclosure_profile", Bson::Null);
+        }
+        if filter.unscanned {
+            let stale = doc! {"$lt": ["$scan_status.as_of_block", {"$add": ["$block", LANDING_WINDOW as i64]}]};
+            document.insert(
+                "$or",
+                vec![
+                    doc! {"scan_status": null},
+                    doc! {"scan_status.status": "error"},
+                    doc! {"scan_status.status": "not_landed", "$expr": stale},
+                ],
+            );
+        }
+        document
+    }
+}
+
+#[async_trait]
+impl EventStore for MongoClient {
+    async fn write_events(&self, events: Vec<Event>) -> Result<WriteSummary> {
+        let mut upserts = vec![];
+        for mut event in events {
+            upserts.push(async move {
+                // An event from history replaces its copy from the live stream, whichever block
+                // that copy was stored under.
+                if event.received_at.is_none() {
+                    event.received_at = self.take_stream_copy(event.hint.hash).await?;
+                }
+                let mut filter = Self::hash_filter(event.hint.hash);
+                filter.insert("block", event.block as i64);
+                let update = doc! {"$setOnInsert": to_document(&event)?};
+                let options = UpdateOptions::builder().upsert(true).build();
+                let res = self.collection.update_one(filter, update, options).await?;
+                Ok::<_, Error>(res.upserted_id.is_some())
+            });
+        }
+        let mut summary = WriteSummary::default();
+        for res in futures::future::join_all(upserts).await {
+            match res? {
+                true => summary.inserted += 1,
+                false => summary.existing += 1,
+            }
+        }
+        Ok(summary)
+    }
+
+    async fn read_events(&self, filter: &EventFilter) -> Result<EventStream<'static>> {
+        let find_options = FindOptions::builder()
+            .batch_size(self.read_batch_size)
+            .allow_disk_use(true)
+            .hint(Self::index_hint(filter))
+            .skip(filter.skip)
+            .limit(filter.limit.map(|limit| limit as i64))
+            .build();
+        let cursor = self
             .collection
-            .insert_many(events, None)
-            .await
-            .unwrap_or_else(|error| panic!("Failed to insert events: {}", error));
-        res.inserted_ids.len() as u64
+            .find(Self::filter_document(filter), find_options)
+            .await?;
+        Ok(cursor.map(|event| Ok(event?)).boxed())
     }
 
-    pub async fn update_event(&self, hash: H256, update: Document) -> u64 {
-        let filter = doc! {"hint.hash": serde_json::to_string(&hash).unwrap().replace('\"', "")};
+    async fn update_event(&self, hash: H256, update: EventUpdate) -> Result<u64> {
+        let mut set = Document::new();
+        if let Some(landed) = update.landed {
+            set.insert("landed", landed);
+        }
+        if let Some(landing) = update.landing {
+            set.insert("landing", to_document(&landing)?);
+        }
+        let mut unset = Document::new();
+        if let Some(refunds) = update.refunds {
+            set.insert("refunds", to_bson(&refunds)?);
+            // Events stored before multiple refunds were recorded have a single `refund` field.
+            unset.insert("refund", "");
+        }
+        if let Some(scan_status) = update.scan_status {
+            set.insert("scan_status", to_bson(&scan_status)?);
+        }
+        if let Some(profile) = update.disclosure_profile {
+            set.insert("disclosure_profile", profile.to_string());
+        }
         let index_hint = IndexHint::Keys(doc! {"hint.hash": 1});
         let options = UpdateOptions::builder()
             .hint(index_hint)
             .bypass_document_validation(true)
             .build();
+        let mut changes = doc! {"$set": set};
+        if !unset.is_empty() {
+            changes.insert("$unset", unset);
+        }
         let res = self
             .collection
-            .update_many(filter, update, options)
-            .await
-            .unwrap_or_else(|error| panic!("Failed to update event: {}", error));
-        res.modified_count
+            .update_many(Self::hash_filter(hash), changes, options)
+            .await?;
+        Ok(res.modified_count)
+    }
+
+    async fn count(&self, filter: &EventFilter) -> Result<u64> {
+        Ok(self
+            .collection
+            .count_documents(Self::filter_document(filter), None)
+            .await?)
+    }
+
+    async fn block_range(&self, filter: &EventFilter) -> Result<Option<(u64, u64)>> {
+        let mut range = vec![];
+        for direction in [1, -1] {
+            let options = FindOneOptions::builder()
+                .sort(doc! {"block": direction})
+                .hint(IndexHint::Keys(doc! {"block": 1}))
+                .build();
+            let event = self
+                .collection
+                .find_one(Self::filter_document(filter), options)
+                .await?;
+            match event {
+                Some(event) => range.push(event.block),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some((range[0], range[1])))
+    }
+
+    async fn read_sync_cursor(
+        &self,
+        block_start: u64,
+        block_end: Option<u64>,
+    ) -> Result<Option<SyncCursor>> {
+        let filter =
+            doc! {"block_start": block_start as i64, "block_end": block_end.map(|end| end as i64)};
+        Ok(self.sync_cursors.find_one(filter, None).await?)
+    }
+
+    async fn write_sync_cursor(&self, cursor: &SyncCursor) -> Result<()> {
+        let filter = doc! {"block_start": cursor.block_start as i64, "block_end": cursor.block_end.map(|end| end as i64)};
+        let options = ReplaceOptions::builder().upsert(true).build();
+        self.sync_cursors
+            .replace_one(filter, cursor, options)
+            .await?;
+        Ok(())
     }
 }
 
 #[cfg(test)]
+#[allow(non_snake_case, clippy::zero_prefixed_literal)]
 mod tests {
-    use std::str::FromStr;
-
     use super::super::super::refunds::{landing::Landing, refund::Refund};
     use super::*;
     use ethers::types::{H160, H256, U256};
     use mev_share::sse::{EventHistory, Hint};
-    use mongodb::{
-        bson::{doc, to_document, Document},
-        options::{AggregateOptions, ClientOptions},
-        Client, Collection,
-    };
 
     const DB_NAME: &str = "mev-share-test";
     const EVENTS_COLLECTION: &str = "events";
@@ -115,13 +339,15 @@ mod tests {
             timestamp: 00000000,
         });
         // Create mongo client
-        let mongo_client = MongoClient::new(MONGO_CONN_STR, DB_NAME, EVENTS_COLLECTION).await;
+        let mongo_client = MongoClient::new(MONGO_CONN_STR, DB_NAME, EVENTS_COLLECTION)
+            .await
+            .unwrap();
 
         // Write event
-        mongo_client.write_event(TEST_EVENT.clone()).await;
+        mongo_client.write_event(TEST_EVENT.clone()).await.unwrap();
 
         // Read event
-        let read_event = mongo_client.read_event(TEST_EVENT.hint.hash).await;
+        let read_event = mongo_client.read_event(TEST_EVENT.hint.hash).await.unwrap();
 
         match read_event {
             Some(event) => assert_eq!(event, TEST_EVENT),
@@ -144,38 +370,150 @@ mod tests {
         });
 
         // Create mongo client
-        let mongo_client = MongoClient::new(MONGO_CONN_STR, DB_NAME, EVENTS_COLLECTION).await;
+        let mongo_client = MongoClient::new(MONGO_CONN_STR, DB_NAME, EVENTS_COLLECTION)
+            .await
+            .unwrap();
 
         // Write event
-        mongo_client.write_event(TEST_EVENT.clone()).await;
+        mongo_client.write_event(TEST_EVENT.clone()).await.unwrap();
 
         // Refund
         let refund = Refund {
             signal_tx: H256::zero(),
             refund_tx: H256::zero(),
-            value: U256::zero().as_u64(),
+            value: U256::zero(),
+            ..Default::default()
         };
-        let refund_doc = to_document(&refund).unwrap();
 
         // Landing
         let landing = Landing {
             block: 00000001,
             timestamp: 00000002,
             builder: H160::random(),
+            ..Default::default()
         };
-        let landing_doc = to_document(&landing).unwrap();
         // Update event
-        let update = doc! {"$set": {"refund": refund_doc, "landing": landing_doc, "landed": true}};
-        let res = mongo_client
+        let update = EventUpdate {
+            landed: Some(true),
+            landing: Some(landing),
+            refunds: Some(vec![refund]),
+            scan_status: None,
+            disclosure_profile: None,
+        };
+        mongo_client
             .update_event(TEST_EVENT.hint.hash, update)
-            .await;
+            .await
+            .unwrap();
 
         // Read event
-        let read_event = mongo_client.read_event(TEST_EVENT.hint.hash).await;
+        let read_event = mongo_client.read_event(TEST_EVENT.hint.hash).await.unwrap();
 
         match read_event {
             Some(event) => assert_eq!(event.block, 00000000),
             None => println!("No event found for provided hash"),
         }
     }
+
+    #[tokio::test]
+    async fn test_remove_duplicates() {
+        let TEST_EVENT: Event = Event::new(EventHistory {
+            hint: Hint {
+                hash: H256::random(),
+                txs: vec![],
+                logs: vec![],
+                mev_gas_price: None,
+                gas_used: None,
+            },
+            block: 00000000,
+            timestamp: 00000000,
+        });
+        let collection_name = format!("events-{:?}", TEST_EVENT.hint.hash);
+        let client = Client::with_uri_str(MONGO_CONN_STR).await.unwrap();
+        let collection = client
+            .database(DB_NAME)
+            .collection::<Event>(&collection_name);
+        collection
+            .insert_many([TEST_EVENT.clone(), TEST_EVENT.clone()], None)
+            .await
+            .unwrap();
+
+        // Creating the unique index removes the duplicate first
+        let mongo_client = MongoClient::new(MONGO_CONN_STR, DB_NAME, &collection_name)
+            .await
+            .unwrap();
+        let filter = EventFilter {
+            hashes: Some(vec![TEST_EVENT.hint.hash]),
+            ..Default::default()
+        };
+        assert_eq!(mongo_client.count(&filter).await.unwrap(), 1);
+        collection.drop(None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_write_stream_then_history() {
+        let TEST_EVENT: Event = Event::new(EventHistory {
+            hint: Hint {
+                hash: H256::random(),
+                txs: vec![],
+                logs: vec![],
+                mev_gas_price: Some(U256::one()),
+                gas_used: Some(U256::one()),
+            },
+            block: 00000001,
+            timestamp: 00000002,
+        });
+        let mongo_client = MongoClient::new(MONGO_CONN_STR, DB_NAME, EVENTS_COLLECTION)
+            .await
+            .unwrap();
+
+        // The stream copy is stored under the same block as the history copy.
+        let streamed = Event::from_stream(
+            mev_share::sse::Event {
+                hash: TEST_EVENT.hint.hash,
+                transactions: vec![],
+                logs: vec![],
+            },
+            TEST_EVENT.block,
+            120_000,
+        );
+        mongo_client.write_events(vec![streamed]).await.unwrap();
+        mongo_client
+            .write_events(vec![TEST_EVENT.clone()])
+            .await
+            .unwrap();
+
+        let filter = EventFilter {
+            hashes: Some(vec![TEST_EVENT.hint.hash]),
+            ..Default::default()
+        };
+        assert_eq!(mongo_client.count(&filter).await.unwrap(), 1);
+        let read_event = mongo_client.read_event(TEST_EVENT.hint.hash).await.unwrap();
+        assert_eq!(
+            read_event,
+            Some(Event {
+                received_at: Some(120_000),
+                ..TEST_EVENT
+            })
+        );
+    }
+
+    #[test]
+    fn test_index_hint() {
+        let keys = |filter: &EventFilter| match MongoClient::index_hint(filter) {
+            Some(IndexHint::Keys(keys)) => Some(keys),
+            _ => None,
+        };
+        let blocks = EventFilter {
+            block_start: Some(1),
+            block_end: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(keys(&blocks), Some(doc! {"block": 1}));
+        let hashes = EventFilter {
+            hashes: Some(vec![H256::zero()]),
+            ..blocks
+        };
+        assert_eq!(keys(&hashes), Some(doc! {"hint.hash": 1}));
+        assert_eq!(keys(&EventFilter::default()), None);
+    }
 }
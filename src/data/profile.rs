@@ -0,0 +1,146 @@
+use crate::error::{Error, Result};
+use mev_share::sse::Hint;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// Hint fields an event revealed, which follow from the privacy settings its sender chose.
+///
+/// The hash is always revealed. Logs are only known to be revealed when the transaction emitted
+/// some, so a profile with `logs` unset may also be one whose transaction emitted none.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+#[serde(into = "String", try_from = "String")]
+pub struct DisclosureProfile {
+    pub contract_address: bool,
+    pub function_selector: bool,
+    pub calldata: bool,
+    pub logs: bool,
+    pub mev_gas_price: bool,
+    pub gas_used: bool,
+}
+
+/// Name of the profile that revealed nothing but the hash.
+const HASH_ONLY: &str = "hash_only";
+
+impl DisclosureProfile {
+    /// Profile of the fields revealed in `hint`.
+    pub fn classify(hint: &Hint) -> Self {
+        DisclosureProfile {
+            contract_address: hint.txs.iter().any(|tx| tx.to.is_some()),
+            function_selector: hint.txs.iter().any(|tx| tx.function_selector.is_some()),
+            calldata: hint.txs.iter().any(|tx| tx.calldata.is_some()),
+            logs: !hint.logs.is_empty(),
+            mev_gas_price: hint.mev_gas_price.is_some(),
+            gas_used: hint.gas_used.is_some(),
+        }
+    }
+
+    fn fields(&self) -> [(&'static str, bool); 6] {
+        [
+            ("contract_address", self.contract_address),
+            ("function_selector", self.function_selector),
+            ("calldata", self.calldata),
+            ("logs", self.logs),
+            ("mev_gas_price", self.mev_gas_price),
+            ("gas_used", self.gas_used),
+        ]
+    }
+}
+
+/// Formats the profile as its revealed fields joined by `+`, e.g. `contract_address+logs`, or
+/// `hash_only` when nothing else was revealed.
+impl fmt::Display for DisclosureProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let revealed: Vec<_> = self
+            .fields()
+            .into_iter()
+            .filter(|(_, revealed)| *revealed)
+            .map(|(name, _)| name)
+            .collect();
+        if revealed.is_empty() {
+            f.write_str(HASH_ONLY)
+        } else {
+            f.write_str(&revealed.join("+"))
+        }
+    }
+}
+
+impl FromStr for DisclosureProfile {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let mut profile = DisclosureProfile::default();
+        if value == HASH_ONLY {
+            return Ok(profile);
+        }
+        for field in value.split('+') {
+            let revealed = match field {
+                "contract_address" => &mut profile.contract_address,
+                "function_selector" => &mut profile.function_selector,
+                "calldata" => &mut profile.calldata,
+                "logs" => &mut profile.logs,
+                "mev_gas_price" => &mut profile.mev_gas_price,
+                "gas_used" => &mut profile.gas_used,
+                _ => return Err(Error::decode("disclosure profile", value)),
+            };
+            *revealed = true;
+        }
+        Ok(profile)
+    }
+}
+
+impl From<DisclosureProfile> for String {
+    fn from(profile: DisclosureProfile) -> Self {
+        profile.to_string()
+    }
+}
+
+impl TryFrom<String> for DisclosureProfile {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{Bytes, H160, H256};
+    use mev_share::sse::{EventTransaction, EventTransactionLog};
+
+    #[test]
+    fn test_classify() {
+        let mut hint = Hint {
+            hash: H256::random(),
+            txs: vec![],
+            logs: vec![],
+            mev_gas_price: None,
+            gas_used: None,
+        };
+        let profile = DisclosureProfile::classify(&hint);
+        assert_eq!(profile.to_string(), "hash_only");
+
+        hint.txs.push(EventTransaction {
+            to: Some(H160::random()),
+            function_selector: None,
+            calldata: None,
+        });
+        hint.logs.push(EventTransactionLog {
+            address: H160::random(),
+            topics: vec![],
+            data: Bytes::default(),
+        });
+        let profile = DisclosureProfile::classify(&hint);
+        assert_eq!(profile.to_string(), "contract_address+logs");
+
+        for profile in [DisclosureProfile::default(), profile] {
+            assert_eq!(
+                profile.to_string().parse::<DisclosureProfile>().unwrap(),
+                profile
+            );
+        }
+        assert!("logs+txs".parse::<DisclosureProfile>().is_err());
+    }
+}
@@ -0,0 +1,1074 @@
+use super::{
+    event::{Event, ScanStatus, LANDING_WINDOW},
+    store::{EventFilter, EventStore, EventStream, EventUpdate, SyncCursor, WriteSummary},
+};
+use crate::{
+    error::{Error, Result},
+    refunds::{backrun::Backrun, landing::Landing, refund::Refund},
+};
+use async_trait::async_trait;
+use ethers::types::{Bytes, H256, U256};
+use futures::StreamExt;
+use mev_share::sse::{EventTransaction, EventTransactionLog, FunctionSelector, Hint};
+use rusqlite::{params, params_from_iter, types::Value, Connection, OptionalExtension, Row};
+use std::{
+    collections::HashMap,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+/// Schema migrations, applied in order. `PRAGMA user_version` records how many have run.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE events (
+        id INTEGER PRIMARY KEY,
+        hash TEXT NOT NULL,
+        block INTEGER NOT NULL,
+        timestamp INTEGER NOT NULL,
+        gas_used TEXT,
+        mev_gas_price TEXT,
+        landed INTEGER
+    );
+    CREATE INDEX events_hash ON events (hash);
+    CREATE INDEX events_block ON events (block);
+
+    CREATE TABLE hint_txs (
+        event_id INTEGER NOT NULL REFERENCES events (id),
+        tx_index INTEGER NOT NULL,
+        to_address TEXT,
+        function_selector TEXT,
+        calldata TEXT,
+        PRIMARY KEY (event_id, tx_index)
+    );
+
+    CREATE TABLE hint_logs (
+        event_id INTEGER NOT NULL REFERENCES events (id),
+        log_index INTEGER NOT NULL,
+        address TEXT NOT NULL,
+        topic0 TEXT,
+        topic1 TEXT,
+        topic2 TEXT,
+        topic3 TEXT,
+        data TEXT NOT NULL,
+        PRIMARY KEY (event_id, log_index)
+    );
+
+    CREATE TABLE landings (
+        event_id INTEGER PRIMARY KEY REFERENCES events (id),
+        block INTEGER NOT NULL,
+        timestamp INTEGER NOT NULL,
+        builder TEXT NOT NULL
+    );
+
+    CREATE TABLE refunds (
+        event_id INTEGER PRIMARY KEY REFERENCES events (id),
+        signal_tx TEXT NOT NULL,
+        refund_tx TEXT NOT NULL,
+        value INTEGER NOT NULL
+    );
+",
+    "
+    ALTER TABLE events ADD COLUMN scan_status TEXT;
+    ALTER TABLE events ADD COLUMN scan_as_of_block INTEGER;
+    ALTER TABLE events ADD COLUMN scan_error TEXT;
+",
+    "
+    CREATE TABLE sync_cursors (
+        block_start INTEGER NOT NULL,
+        block_end INTEGER,
+        offset INTEGER NOT NULL,
+        info_count INTEGER NOT NULL
+    );
+",
+    "
+    DELETE FROM events WHERE id NOT IN (SELECT MIN(id) FROM events GROUP BY hash, block);
+    DELETE FROM hint_txs WHERE event_id NOT IN (SELECT id FROM events);
+    DELETE FROM hint_logs WHERE event_id NOT IN (SELECT id FROM events);
+    DELETE FROM landings WHERE event_id NOT IN (SELECT id FROM events);
+    DELETE FROM refunds WHERE event_id NOT IN (SELECT id FROM events);
+    CREATE UNIQUE INDEX events_hash_block ON events (hash, block);
+",
+    "
+    ALTER TABLE events ADD COLUMN received_at INTEGER;
+",
+    "
+    ALTER TABLE landings ADD COLUMN success INTEGER;
+    ALTER TABLE landings ADD COLUMN gas_used INTEGER;
+    ALTER TABLE landings ADD COLUMN effective_gas_price TEXT;
+    ALTER TABLE landings ADD COLUMN tx_index INTEGER;
+",
+    "
+    ALTER TABLE refunds ADD COLUMN recipient TEXT;
+    ALTER TABLE refunds ADD COLUMN rule TEXT;
+",
+    "
+    ALTER TABLE refunds RENAME COLUMN value TO value_u64;
+    ALTER TABLE refunds ADD COLUMN value TEXT;
+",
+    "
+    CREATE TABLE refunds_by_index (
+        event_id INTEGER NOT NULL REFERENCES events (id),
+        refund_index INTEGER NOT NULL,
+        signal_tx TEXT NOT NULL,
+        refund_tx TEXT NOT NULL,
+        value TEXT NOT NULL,
+        recipient TEXT,
+        rule TEXT,
+        PRIMARY KEY (event_id, refund_index)
+    );
+    INSERT INTO refunds_by_index
+        SELECT event_id, 0, signal_tx, refund_tx, value, recipient, rule FROM refunds;
+    DROP TABLE refunds;
+    ALTER TABLE refunds_by_index RENAME TO refunds;
+",
+    "
+    CREATE TABLE backruns (
+        event_id INTEGER NOT NULL REFERENCES events (id),
+        backrun_index INTEGER NOT NULL,
+        hash TEXT NOT NULL,
+        sender TEXT NOT NULL,
+        gas_used INTEGER,
+        coinbase_payment TEXT NOT NULL,
+        PRIMARY KEY (event_id, backrun_index)
+    );
+",
+    "
+    ALTER TABLE landings ADD COLUMN builder_name TEXT;
+    ALTER TABLE landings ADD COLUMN proposer_payment TEXT;
+",
+    "
+    ALTER TABLE events ADD COLUMN disclosure_profile TEXT;
+",
+];
+
+/// Number of events loaded per query when streaming from [`SqliteStore::read_events`].
+const PAGE_SIZE: u64 = 10_000;
+
+const EVENT_COLUMNS: &str = "events.id, events.hash, events.block, events.timestamp, events.gas_used, events.mev_gas_price, events.landed, \
+    landings.block, landings.timestamp, landings.builder, \
+    events.scan_status, events.scan_as_of_block, events.scan_error, events.received_at, \
+    landings.success, landings.gas_used, landings.effective_gas_price, landings.tx_index, \
+    landings.builder_name, landings.proposer_payment, events.disclosure_profile";
+
+/// Event store backed by a single SQLite database file.
+#[derive(Clone)]
+pub struct SqliteStore {
+    pub conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    /// Opens (or creates) the database at `path` and brings its schema up to date.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Opens a private in-memory database.
+    pub fn in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut conn: Connection) -> Result<Self> {
+        migrate(&mut conn)?;
+        Ok(SqliteStore {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Runs `f` with the connection on the blocking thread pool.
+    async fn with_conn<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Connection) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || f(&mut conn.lock().unwrap()))
+            .await
+            .unwrap()
+    }
+}
+
+#[async_trait]
+impl EventStore for SqliteStore {
+    async fn write_events(&self, events: Vec<Event>) -> Result<WriteSummary> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let mut summary = WriteSummary::default();
+            for event in &events {
+                match insert_event(&tx, event)? {
+                    true => summary.inserted += 1,
+                    false => summary.existing += 1,
+                }
+            }
+            tx.commit()?;
+            Ok(summary)
+        })
+        .await
+    }
+
+    async fn read_events(&self, filter: &EventFilter) -> Result<EventStream<'static>> {
+        let store = self.clone();
+        let filter = filter.clone();
+        // (last id read, events still to return, offset for the first page); `None` once a page
+        // failed, so the error is the last item of the stream.
+        let state = Some((
+            0_i64,
+            filter.limit.unwrap_or(u64::MAX),
+            filter.skip.unwrap_or(0),
+        ));
+        let events = futures::stream::unfold(state, move |state| {
+            let store = store.clone();
+            let filter = filter.clone();
+            async move {
+                let (after_id, remaining, skip) = state?;
+                if remaining == 0 {
+                    return None;
+                }
+                let page_size = remaining.min(PAGE_SIZE);
+                let page = store
+                    .with_conn(move |conn| read_page(conn, &filter, after_id, page_size, skip))
+                    .await;
+                match page {
+                    Ok(page) => {
+                        let (last_id, _) = page.last()?;
+                        let state = Some((*last_id, remaining - page.len() as u64, 0));
+                        let events: Vec<Result<Event>> =
+                            page.into_iter().map(|(_, event)| Ok(event)).collect();
+                        Some((events, state))
+                    }
+                    Err(error) => Some((vec![Err(error)], None)),
+                }
+            }
+        });
+        Ok(events.flat_map(futures::stream::iter).boxed())
+    }
+
+    async fn update_event(&self, hash: H256, update: EventUpdate) -> Result<u64> {
+        self.with_conn(move |conn| Ok(update_event(conn, hash, update)?))
+            .await
+    }
+
+    async fn count(&self, filter: &EventFilter) -> Result<u64> {
+        let filter = filter.clone();
+        self.with_conn(move |conn| {
+            let (clause, values) = where_clause(&filter);
+            let count = conn.query_row(
+                &format!("SELECT COUNT(*) FROM events WHERE {}", clause),
+                params_from_iter(values),
+                |row| row.get::<_, i64>(0),
+            )?;
+            Ok(count as u64)
+        })
+        .await
+    }
+
+    async fn block_range(&self, filter: &EventFilter) -> Result<Option<(u64, u64)>> {
+        let filter = filter.clone();
+        let (min, max) = self
+            .with_conn(move |conn| {
+                let (clause, values) = where_clause(&filter);
+                conn.query_row(
+                    &format!("SELECT MIN(block), MAX(block) FROM events WHERE {}", clause),
+                    params_from_iter(values),
+                    |row| Ok((row.get::<_, Option<i64>>(0)?, row.get::<_, Option<i64>>(1)?)),
+                )
+            })
+            .await?;
+        Ok(min.zip(max).map(|(min, max)| (min as u64, max as u64)))
+    }
+
+    async fn read_sync_cursor(
+        &self,
+        block_start: u64,
+        block_end: Option<u64>,
+    ) -> Result<Option<SyncCursor>> {
+        self.with_conn(move |conn| {
+            let cursor = conn.query_row(
+                "SELECT offset, info_count FROM sync_cursors WHERE block_start = ? AND block_end IS ?",
+                params![block_start as i64, block_end.map(|end| end as i64)],
+                |row| {
+                    Ok(SyncCursor {
+                        block_start,
+                        block_end,
+                        offset: row.get::<_, i64>(0)? as u64,
+                        info_count: row.get::<_, i64>(1)? as u64,
+                    })
+                },
+            )
+            .optional()?;
+            Ok(cursor)
+        })
+        .await
+    }
+
+    async fn write_sync_cursor(&self, cursor: &SyncCursor) -> Result<()> {
+        let cursor = cursor.clone();
+        self.with_conn(move |conn| {
+            let range = params![cursor.block_start as i64, cursor.block_end.map(|end| end as i64)];
+            let tx = conn.transaction()?;
+            tx.execute(
+                "DELETE FROM sync_cursors WHERE block_start = ? AND block_end IS ?",
+                range,
+            )?;
+            tx.execute(
+                "INSERT INTO sync_cursors (block_start, block_end, offset, info_count) VALUES (?, ?, ?, ?)",
+                params![
+                    cursor.block_start as i64,
+                    cursor.block_end.map(|end| end as i64),
+                    cursor.offset as i64,
+                    cursor.info_count as i64,
+                ],
+            )?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        if i + 1 == 8 {
+            migrate_refund_values(&tx)?;
+        }
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Completes migration 8 by copying the integer refund values into the decimal `value` column.
+/// Values were written as `u64` cast to `i64`, so negative ones wrapped around from above `2^63`.
+fn migrate_refund_values(conn: &Connection) -> rusqlite::Result<()> {
+    let values = conn
+        .prepare("SELECT rowid, value_u64 FROM refunds")?
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (rowid, value) in values {
+        conn.execute(
+            "UPDATE refunds SET value = ? WHERE rowid = ?",
+            params![(value as u64).to_string(), rowid],
+        )?;
+    }
+    conn.execute_batch("ALTER TABLE refunds DROP COLUMN value_u64")
+}
+
+/// Builds the `WHERE` clause (without skip/limit) for `filter` over the `events` table.
+fn where_clause(filter: &EventFilter) -> (String, Vec<Value>) {
+    let mut clauses = vec!["1 = 1".to_string()];
+    let mut values = vec![];
+    if let Some(block_start) = filter.block_start {
+        clauses.push("events.block >= ?".into());
+        values.push(Value::Integer(block_start as i64));
+    }
+    if let Some(block_end) = filter.block_end {
+        clauses.push("events.block <= ?".into());
+        values.push(Value::Integer(block_end as i64));
+    }
+    if let Some(timestamp_start) = filter.timestamp_start {
+        clauses.push("events.timestamp >= ?".into());
+        values.push(Value::Integer(timestamp_start as i64));
+    }
+    if let Some(timestamp_end) = filter.timestamp_end {
+        clauses.push("events.timestamp <= ?".into());
+        values.push(Value::Integer(timestamp_end as i64));
+    }
+    if filter.unscanned {
+        clauses.push(
+            "(events.scan_status IS NULL OR events.scan_status = 'error' \
+             OR (events.scan_status = 'not_landed' AND events.scan_as_of_block < events.block + ?))"
+                .into(),
+        );
+        values.push(Value::Integer(LANDING_WINDOW as i64));
+    }
+    if filter.unclassified {
+        clauses.push("events.disclosure_profile IS NULL".into());
+    }
+    if let Some(hashes) = &filter.hashes {
+        let placeholders = vec!["?"; hashes.len()].join(", ");
+        clauses.push(format!("events.hash IN ({})", placeholders));
+        values.extend(hashes.iter().map(|hash| Value::Text(hex(hash))));
+    }
+    (clauses.join(" AND "), values)
+}
+
+/// Deletes the copy of the event with hint hash `hash` received from the live stream, if any, and
+/// returns its receive time.
+fn take_stream_copy(conn: &Connection, hash: &H256) -> rusqlite::Result<Option<u64>> {
+    let copy: Option<(i64, i64)> = conn
+        .query_row(
+            "SELECT id, received_at FROM events \
+             WHERE hash = ? AND received_at IS NOT NULL AND disclosure_profile IS NULL",
+            [hex(hash)],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let Some((copy_id, received_at)) = copy else {
+        return Ok(None);
+    };
+    for table in ["hint_txs", "hint_logs", "landings", "refunds", "backruns"] {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE event_id = ?", table),
+            [copy_id],
+        )?;
+    }
+    conn.execute("DELETE FROM events WHERE id = ?", [copy_id])?;
+    Ok(Some(received_at as u64))
+}
+
+/// Inserts `event` unless an event with the same hash and block exists, returning whether it was inserted.
+/// An event from history replaces its copy from the live stream, keeping its receive time.
+fn insert_event(conn: &Connection, event: &Event) -> rusqlite::Result<bool> {
+    let received_at = match event.received_at {
+        Some(received_at) => Some(received_at),
+        None => take_stream_copy(conn, &event.hint.hash)?,
+    };
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO events (hash, block, timestamp, gas_used, mev_gas_price, landed, received_at, disclosure_profile) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            hex(&event.hint.hash),
+            event.block as i64,
+            event.timestamp as i64,
+            event.hint.gas_used.map(|gas| gas.to_string()),
+            event.hint.mev_gas_price.map(|price| price.to_string()),
+            event.landed,
+            received_at.map(|received_at| received_at as i64),
+            event.disclosure_profile.map(|profile| profile.to_string()),
+        ],
+    )?;
+    if inserted == 0 {
+        return Ok(false);
+    }
+    let event_id = conn.last_insert_rowid();
+
+    for (i, tx) in event.hint.txs.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO hint_txs (event_id, tx_index, to_address, function_selector, calldata) VALUES (?, ?, ?, ?, ?)",
+            params![
+                event_id,
+                i as i64,
+                tx.to.as_ref().map(hex),
+                tx.function_selector.as_ref().map(|selector| selector.to_string()),
+                tx.calldata.as_ref().map(|calldata| calldata.to_string()),
+            ],
+        )?;
+    }
+    for (i, log) in event.hint.logs.iter().enumerate() {
+        let topic = |n: usize| log.topics.get(n).map(hex);
+        conn.execute(
+            "INSERT INTO hint_logs (event_id, log_index, address, topic0, topic1, topic2, topic3, data) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                event_id,
+                i as i64,
+                hex(&log.address),
+                topic(0),
+                topic(1),
+                topic(2),
+                topic(3),
+                log.data.to_string(),
+            ],
+        )?;
+    }
+    if let Some(landing) = &event.landing {
+        insert_landing(conn, event_id, landing)?;
+    }
+    insert_refunds(conn, event_id, &event.refunds)?;
+    if let Some(scan_status) = &event.scan_status {
+        update_scan_status(conn, event_id, scan_status)?;
+    }
+    Ok(true)
+}
+
+fn update_scan_status(
+    conn: &Connection,
+    event_id: i64,
+    scan_status: &ScanStatus,
+) -> rusqlite::Result<()> {
+    let (as_of_block, error) = match scan_status {
+        ScanStatus::NotLanded { as_of_block } => (Some(*as_of_block as i64), None),
+        ScanStatus::Error { message } => (None, Some(message.as_str())),
+        ScanStatus::Landed | ScanStatus::Refunded => (None, None),
+    };
+    conn.execute(
+        "UPDATE events SET scan_status = ?, scan_as_of_block = ?, scan_error = ? WHERE id = ?",
+        params![scan_status.as_str(), as_of_block, error, event_id],
+    )?;
+    Ok(())
+}
+
+fn scan_status_from_row(row: &Row<'_>, idx: usize) -> rusqlite::Result<Option<ScanStatus>> {
+    let status: Option<String> = row.get(idx)?;
+    let scan_status = match status.as_deref() {
+        None => None,
+        Some("not_landed") => Some(ScanStatus::NotLanded {
+            as_of_block: row.get::<_, i64>(idx + 1)? as u64,
+        }),
+        Some("landed") => Some(ScanStatus::Landed),
+        Some("refunded") => Some(ScanStatus::Refunded),
+        Some(_) => Some(ScanStatus::Error {
+            message: row.get::<_, Option<String>>(idx + 2)?.unwrap_or_default(),
+        }),
+    };
+    Ok(scan_status)
+}
+
+fn insert_landing(conn: &Connection, event_id: i64, landing: &Landing) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO landings (event_id, block, timestamp, builder, success, gas_used, effective_gas_price, tx_index, \
+         builder_name, proposer_payment) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            event_id,
+            landing.block as i64,
+            landing.timestamp as i64,
+            hex(&landing.builder),
+            landing.success,
+            landing.gas_used.map(|gas| gas as i64),
+            landing.effective_gas_price.map(|price| price.to_string()),
+            landing.tx_index.map(|index| index as i64),
+            landing.builder_name,
+            landing.proposer_payment.as_ref().map(hex),
+        ],
+    )?;
+    conn.execute("DELETE FROM backruns WHERE event_id = ?", [event_id])?;
+    for (i, backrun) in landing.backruns.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO backruns (event_id, backrun_index, hash, sender, gas_used, coinbase_payment) \
+             VALUES (?, ?, ?, ?, ?, ?)",
+            params![
+                event_id,
+                i as i64,
+                hex(&backrun.hash),
+                hex(&backrun.from),
+                backrun.gas_used.map(|gas| gas as i64),
+                backrun.coinbase_payment.to_string(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Replaces the refunds of event `event_id` with `refunds`.
+fn insert_refunds(conn: &Connection, event_id: i64, refunds: &[Refund]) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM refunds WHERE event_id = ?", [event_id])?;
+    for (i, refund) in refunds.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO refunds (event_id, refund_index, signal_tx, refund_tx, value, recipient, rule) \
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                event_id,
+                i as i64,
+                hex(&refund.signal_tx),
+                hex(&refund.refund_tx),
+                refund.value.to_string(),
+                hex(&refund.recipient),
+                refund.rule.as_str(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn update_event(conn: &mut Connection, hash: H256, update: EventUpdate) -> rusqlite::Result<u64> {
+    let tx = conn.transaction()?;
+    let ids = {
+        let mut stmt = tx.prepare("SELECT id FROM events WHERE hash = ?")?;
+        let ids = stmt.query_map([hex(&hash)], |row| row.get::<_, i64>(0))?;
+        ids.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+    for id in &ids {
+        if let Some(landed) = update.landed {
+            tx.execute(
+                "UPDATE events SET landed = ? WHERE id = ?",
+                params![landed, id],
+            )?;
+        }
+        if let Some(landing) = &update.landing {
+            insert_landing(&tx, *id, landing)?;
+        }
+        if let Some(refunds) = &update.refunds {
+            insert_refunds(&tx, *id, refunds)?;
+        }
+        if let Some(scan_status) = &update.scan_status {
+            update_scan_status(&tx, *id, scan_status)?;
+        }
+        if let Some(profile) = update.disclosure_profile {
+            tx.execute(
+                "UPDATE events SET disclosure_profile = ? WHERE id = ?",
+                params![profile.to_string(), id],
+            )?;
+        }
+    }
+    tx.commit()?;
+    Ok(ids.len() as u64)
+}
+
+/// Reads up to `page_size` events with `id > after_id` matching `filter`, in id order.
+fn read_page(
+    conn: &mut Connection,
+    filter: &EventFilter,
+    after_id: i64,
+    page_size: u64,
+    skip: u64,
+) -> Result<Vec<(i64, Event)>> {
+    let (clause, mut values) = where_clause(filter);
+    values.extend([after_id, page_size as i64, skip as i64].map(Value::Integer));
+    let sql = format!(
+        "SELECT {} FROM events \
+         LEFT JOIN landings ON landings.event_id = events.id \
+         WHERE {} AND events.id > ? ORDER BY events.id LIMIT ? OFFSET ?",
+        EVENT_COLUMNS, clause
+    );
+    let mut events = vec![];
+    {
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(params_from_iter(values))?;
+        while let Some(row) = rows.next()? {
+            events.push(event_from_row(row)?);
+        }
+    }
+    let (Some((first_id, _)), Some((last_id, _))) = (events.first(), events.last()) else {
+        return Ok(events);
+    };
+    let (first_id, last_id) = (*first_id, *last_id);
+    let positions: HashMap<i64, usize> = events
+        .iter()
+        .enumerate()
+        .map(|(i, (id, _))| (*id, i))
+        .collect();
+
+    let mut stmt = conn.prepare(
+        "SELECT event_id, to_address, function_selector, calldata FROM hint_txs \
+         WHERE event_id BETWEEN ? AND ? ORDER BY event_id, tx_index",
+    )?;
+    let mut rows = stmt.query([first_id, last_id])?;
+    while let Some(row) = rows.next()? {
+        if let Some(i) = positions.get(&row.get::<_, i64>(0)?) {
+            let selector: Option<String> = row.get(2)?;
+            events[*i].1.hint.txs.push(EventTransaction {
+                to: parse_opt(row.get(1)?)?,
+                function_selector: selector.as_deref().map(parse_selector).transpose()?,
+                calldata: parse_opt(row.get(3)?)?,
+            });
+        }
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT event_id, address, topic0, topic1, topic2, topic3, data FROM hint_logs \
+         WHERE event_id BETWEEN ? AND ? ORDER BY event_id, log_index",
+    )?;
+    let mut rows = stmt.query([first_id, last_id])?;
+    while let Some(row) = rows.next()? {
+        if let Some(i) = positions.get(&row.get::<_, i64>(0)?) {
+            let mut topics = vec![];
+            for n in 2..6 {
+                match parse_opt(row.get(n)?)? {
+                    Some(topic) => topics.push(topic),
+                    None => break,
+                }
+            }
+            events[*i].1.hint.logs.push(EventTransactionLog {
+                address: parse(&row.get::<_, String>(1)?)?,
+                topics,
+                data: parse(&row.get::<_, String>(6)?)?,
+            });
+        }
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT event_id, signal_tx, refund_tx, value, recipient, rule FROM refunds \
+         WHERE event_id BETWEEN ? AND ? ORDER BY event_id, refund_index",
+    )?;
+    let mut rows = stmt.query([first_id, last_id])?;
+    while let Some(row) = rows.next()? {
+        if let Some(i) = positions.get(&row.get::<_, i64>(0)?) {
+            events[*i].1.refunds.push(Refund {
+                signal_tx: parse(&row.get::<_, String>(1)?)?,
+                refund_tx: parse(&row.get::<_, String>(2)?)?,
+                value: parse_dec(&row.get::<_, String>(3)?)?,
+                // Refunds scanned before recipients were recorded were all paid to the sender.
+                recipient: parse_opt(row.get(4)?)?.unwrap_or_default(),
+                rule: parse_opt(row.get(5)?)?.unwrap_or_default(),
+            });
+        }
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT event_id, hash, sender, gas_used, coinbase_payment FROM backruns \
+         WHERE event_id BETWEEN ? AND ? ORDER BY event_id, backrun_index",
+    )?;
+    let mut rows = stmt.query([first_id, last_id])?;
+    while let Some(row) = rows.next()? {
+        let landing = positions
+            .get(&row.get::<_, i64>(0)?)
+            .and_then(|i| events[*i].1.landing.as_mut());
+        if let Some(landing) = landing {
+            landing.backruns.push(Backrun {
+                hash: parse(&row.get::<_, String>(1)?)?,
+                from: parse(&row.get::<_, String>(2)?)?,
+                gas_used: row.get::<_, Option<i64>>(3)?.map(|gas| gas as u64),
+                coinbase_payment: parse_dec(&row.get::<_, String>(4)?)?,
+            });
+        }
+    }
+
+    Ok(events)
+}
+
+fn event_from_row(row: &Row<'_>) -> Result<(i64, Event)> {
+    let landing = row
+        .get::<_, Option<i64>>(7)?
+        .map(|block| -> Result<Landing> {
+            Ok(Landing {
+                block: block as u64,
+                timestamp: row.get::<_, i64>(8)? as u64,
+                builder: parse(&row.get::<_, String>(9)?)?,
+                builder_name: row.get(18)?,
+                proposer_payment: parse_opt(row.get(19)?)?,
+                success: row.get(14)?,
+                gas_used: row.get::<_, Option<i64>>(15)?.map(|gas| gas as u64),
+                effective_gas_price: row
+                    .get::<_, Option<String>>(16)?
+                    .as_deref()
+                    .map(parse_dec)
+                    .transpose()?,
+                tx_index: row.get::<_, Option<i64>>(17)?.map(|index| index as u64),
+                backruns: vec![],
+            })
+        })
+        .transpose()?;
+    let gas_used: Option<String> = row.get(4)?;
+    let mev_gas_price: Option<String> = row.get(5)?;
+    let event = Event {
+        block: row.get::<_, i64>(2)? as u64,
+        timestamp: row.get::<_, i64>(3)? as u64,
+        hint: Hint {
+            hash: parse(&row.get::<_, String>(1)?)?,
+            txs: vec![],
+            logs: vec![],
+            gas_used: gas_used.as_deref().map(parse_dec).transpose()?,
+            mev_gas_price: mev_gas_price.as_deref().map(parse_dec).transpose()?,
+        },
+        refunds: vec![],
+        landing,
+        landed: row.get(6)?,
+        scan_status: scan_status_from_row(row, 10)?,
+        received_at: row
+            .get::<_, Option<i64>>(13)?
+            .map(|received_at| received_at as u64),
+        disclosure_profile: parse_opt(row.get(20)?)?,
+    };
+    Ok((row.get(0)?, event))
+}
+
+/// Full `0x`-prefixed hex encoding, as used for hashes and addresses in the schema.
+fn hex<T: std::fmt::Debug>(value: &T) -> String {
+    format!("{:?}", value)
+}
+
+fn parse<T: FromStr>(value: &str) -> Result<T>
+where
+    T::Err: std::fmt::Debug,
+{
+    value
+        .parse()
+        .map_err(|error| Error::decode(value, format!("{:?}", error)))
+}
+
+fn parse_opt<T: FromStr>(value: Option<String>) -> Result<Option<T>>
+where
+    T::Err: std::fmt::Debug,
+{
+    value.as_deref().map(parse).transpose()
+}
+
+fn parse_dec(value: &str) -> Result<U256> {
+    U256::from_dec_str(value).map_err(|error| Error::decode(value, error))
+}
+
+fn parse_selector(value: &str) -> Result<FunctionSelector> {
+    let bytes: Bytes = parse(value)?;
+    FunctionSelector::try_from(bytes.as_ref()).map_err(|error| Error::decode(value, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::refunds::refund::RefundRule;
+    use ethers::types::H160;
+    use mev_share::sse::{self, EventHistory};
+
+    fn test_event(block: u64) -> Event {
+        Event::new(EventHistory {
+            hint: Hint {
+                hash: H256::random(),
+                txs: vec![EventTransaction {
+                    to: Some(H160::random()),
+                    function_selector: Some(FunctionSelector([0x12, 0x34, 0x56, 0x78])),
+                    calldata: None,
+                }],
+                logs: vec![EventTransactionLog {
+                    address: H160::random(),
+                    topics: vec![H256::random(), H256::random()],
+                    data: Bytes::from(vec![1, 2, 3]),
+                }],
+                mev_gas_price: Some(U256::from(42)),
+                gas_used: None,
+            },
+            block,
+            timestamp: block * 12,
+        })
+    }
+
+    async fn read_all(store: &SqliteStore, filter: &EventFilter) -> Vec<Event> {
+        let events = store.read_events(filter).await.unwrap();
+        events.map(Result::unwrap).collect().await
+    }
+
+    #[tokio::test]
+    async fn test_write_and_read_events() {
+        let store = SqliteStore::in_memory().unwrap();
+        let events: Vec<Event> = (0..5).map(test_event).collect();
+        let summary = store.write_events(events.clone()).await.unwrap();
+        assert_eq!(summary.inserted, 5);
+
+        // Rewriting an overlapping range only inserts the new events.
+        let mut overlapping = events[3..].to_vec();
+        overlapping.push(test_event(5));
+        let summary = store.write_events(overlapping).await.unwrap();
+        assert_eq!(
+            summary,
+            WriteSummary {
+                inserted: 1,
+                existing: 2,
+            }
+        );
+        assert_eq!(store.count(&EventFilter::default()).await.unwrap(), 6);
+
+        let filter = EventFilter {
+            block_start: Some(1),
+            block_end: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(store.count(&filter).await.unwrap(), 3);
+        assert_eq!(store.block_range(&filter).await.unwrap(), Some((1, 3)));
+        let read = read_all(&store, &filter).await;
+        assert_eq!(read, events[1..4]);
+
+        let filter = EventFilter {
+            skip: Some(2),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let read = read_all(&store, &filter).await;
+        assert_eq!(read, events[2..4]);
+
+        let filter = EventFilter {
+            hashes: Some(vec![
+                events[4].hint.hash,
+                events[0].hint.hash,
+                H256::random(),
+            ]),
+            ..Default::default()
+        };
+        let read = read_all(&store, &filter).await;
+        assert_eq!(read, vec![events[0].clone(), events[4].clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_update_event() {
+        let store = SqliteStore::in_memory().unwrap();
+        let event = test_event(1);
+        store.write_events(vec![event.clone()]).await.unwrap();
+
+        let landing = Landing {
+            block: 2,
+            timestamp: 24,
+            builder: H160::random(),
+            success: Some(false),
+            gas_used: Some(21_000),
+            effective_gas_price: Some(U256::from(30_000_000_000_u64)),
+            tx_index: Some(3),
+            builder_name: Some("flashbots".into()),
+            proposer_payment: Some(H256::random()),
+            backruns: vec![Backrun {
+                hash: H256::random(),
+                from: H160::random(),
+                gas_used: Some(150_000),
+                coinbase_payment: U256::exp10(18),
+            }],
+        };
+        let refunds = vec![
+            Refund {
+                signal_tx: event.hint.hash,
+                refund_tx: H256::random(),
+                value: U256::exp10(20),
+                recipient: H160::random(),
+                rule: RefundRule::Config,
+            },
+            Refund {
+                signal_tx: event.hint.hash,
+                refund_tx: H256::random(),
+                value: U256::from(1_000),
+                ..Default::default()
+            },
+        ];
+        let update = EventUpdate {
+            landed: Some(true),
+            landing: Some(landing.clone()),
+            refunds: Some(refunds.clone()),
+            scan_status: Some(ScanStatus::Refunded),
+            ..Default::default()
+        };
+        assert_eq!(
+            store.update_event(event.hint.hash, update).await.unwrap(),
+            1
+        );
+
+        let read = read_all(&store, &EventFilter::default()).await;
+        assert_eq!(read[0].landed, Some(true));
+        assert_eq!(read[0].landing, Some(landing));
+        assert_eq!(read[0].refunds, refunds);
+        assert_eq!(read[0].scan_status, Some(ScanStatus::Refunded));
+
+        // A rescan replaces the stored refunds.
+        let update = EventUpdate {
+            refunds: Some(refunds[1..].to_vec()),
+            ..Default::default()
+        };
+        store.update_event(event.hint.hash, update).await.unwrap();
+        let read = read_all(&store, &EventFilter::default()).await;
+        assert_eq!(read[0].refunds, refunds[1..]);
+    }
+
+    #[tokio::test]
+    async fn test_write_stream_then_history() {
+        // The stream copy is stored under the node's block, usually the block of the history copy.
+        for stream_block in [11, 10] {
+            let store = SqliteStore::in_memory().unwrap();
+            let event = test_event(11);
+            let hint = event.hint.clone();
+            let streamed = Event::from_stream(
+                sse::Event {
+                    hash: hint.hash,
+                    transactions: hint.txs,
+                    logs: hint.logs,
+                },
+                stream_block,
+                120_000,
+            );
+            store.write_events(vec![streamed]).await.unwrap();
+
+            // The history copy replaces the stream copy.
+            store.write_events(vec![event.clone()]).await.unwrap();
+            let read = read_all(&store, &EventFilter::default()).await;
+            assert_eq!(
+                read,
+                vec![Event {
+                    received_at: Some(120_000),
+                    ..event.clone()
+                }]
+            );
+
+            // Refetching history leaves it alone.
+            let summary = store.write_events(vec![event]).await.unwrap();
+            assert_eq!(summary.existing, 1);
+            assert_eq!(store.count(&EventFilter::default()).await.unwrap(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_migrate_refund_values() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        // Refund values were stored as integers before migration 8.
+        let before = 7;
+        for migration in &MIGRATIONS[..before] {
+            conn.execute_batch(migration).unwrap();
+        }
+        conn.pragma_update(None, "user_version", before).unwrap();
+        conn.execute_batch(
+            "INSERT INTO events (id, hash, block, timestamp) VALUES (1, '0x01', 1, 1), (2, '0x02', 1, 1);
+             INSERT INTO refunds (event_id, signal_tx, refund_tx, value) VALUES (1, '0x01', '0x02', 1000);
+             INSERT INTO refunds (event_id, signal_tx, refund_tx, value) VALUES (2, '0x02', '0x03', -1);",
+        )
+        .unwrap();
+        migrate(&mut conn).unwrap();
+
+        let values: Vec<String> = conn
+            .prepare("SELECT value FROM refunds ORDER BY event_id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(values, vec!["1000", "18446744073709551615"]);
+    }
+
+    #[tokio::test]
+    async fn test_read_undecodable_event() {
+        let store = SqliteStore::in_memory().unwrap();
+        store.write_events(vec![test_event(1)]).await.unwrap();
+        store
+            .conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE events SET hash = 'not a hash'", [])
+            .unwrap();
+
+        let events = store.read_events(&EventFilter::default()).await.unwrap();
+        let read: Vec<Result<Event>> = events.collect().await;
+        assert_eq!(read.len(), 1);
+        assert!(matches!(read[0], Err(Error::Decode { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_sync_cursor() {
+        let store = SqliteStore::in_memory().unwrap();
+        assert_eq!(store.read_sync_cursor(1, None).await.unwrap(), None);
+
+        let mut cursor = SyncCursor {
+            block_start: 1,
+            block_end: None,
+            offset: 500,
+            info_count: 1_000,
+        };
+        store.write_sync_cursor(&cursor).await.unwrap();
+        cursor.offset = 1_000;
+        store.write_sync_cursor(&cursor).await.unwrap();
+        assert_eq!(store.read_sync_cursor(1, None).await.unwrap(), Some(cursor));
+        assert_eq!(store.read_sync_cursor(1, Some(10)).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_unscanned_filter() {
+        let store = SqliteStore::in_memory().unwrap();
+        let events: Vec<Event> = (0..4).map(|_| test_event(100)).collect();
+        store.write_events(events.clone()).await.unwrap();
+
+        let statuses = [
+            ScanStatus::Landed,
+            ScanStatus::NotLanded { as_of_block: 101 },
+            ScanStatus::NotLanded {
+                as_of_block: 100 + LANDING_WINDOW,
+            },
+        ];
+        for (event, scan_status) in events.iter().zip(statuses) {
+            let update = EventUpdate {
+                scan_status: Some(scan_status),
+                ..Default::default()
+            };
+            store.update_event(event.hint.hash, update).await.unwrap();
+        }
+
+        let filter = EventFilter {
+            unscanned: true,
+            ..Default::default()
+        };
+        let read = read_all(&store, &filter).await;
+        let hashes: Vec<H256> = read.iter().map(|event| event.hint.hash).collect();
+        assert_eq!(hashes, vec![events[1].hint.hash, events[3].hint.hash]);
+    }
+}
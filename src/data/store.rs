@@ -0,0 +1,145 @@
+use super::{
+    event::{Event, ScanStatus},
+    profile::DisclosureProfile,
+};
+use crate::{
+    error::Result,
+    refunds::{landing::Landing, refund::Refund},
+};
+use async_trait::async_trait;
+use ethers::types::H256;
+use futures::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+
+/// Stream of events returned by [`EventStore::read_events`]. An item is an error when reading or
+/// decoding the stored events failed.
+pub type EventStream<'a> = BoxStream<'a, Result<Event>>;
+
+/// Typed query over stored events. Unset fields do not constrain the query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    /// Inclusive lower bound on `Event.block`.
+    pub block_start: Option<u64>,
+    /// Inclusive upper bound on `Event.block`.
+    pub block_end: Option<u64>,
+    /// Inclusive lower bound on `Event.timestamp`.
+    pub timestamp_start: Option<u64>,
+    /// Inclusive upper bound on `Event.timestamp`.
+    pub timestamp_end: Option<u64>,
+    /// Number of matching events to skip.
+    pub skip: Option<u64>,
+    /// Maximum number of events to return.
+    pub limit: Option<u64>,
+    /// Only match events that were never scanned, or whose [`ScanStatus`] is not final.
+    pub unscanned: bool,
+    /// Only match events whose hint hash is one of these.
+    pub hashes: Option<Vec<H256>>,
+    /// Only match events without a disclosure profile.
+    pub unclassified: bool,
+}
+
+impl EventFilter {
+    /// Splits `block_start..=block_end` into at most `parts` contiguous sub-ranges, each returned as
+    /// a copy of this filter restricted to that range. An empty range returns no sub-ranges.
+    pub fn split_blocks(&self, block_start: u64, block_end: u64, parts: u64) -> Vec<EventFilter> {
+        if block_end < block_start {
+            return vec![];
+        }
+        let blocks = block_end - block_start + 1;
+        let blocks_per_part = blocks.div_ceil(parts.max(1));
+        (block_start..=block_end)
+            .step_by(blocks_per_part as usize)
+            .map(|start| EventFilter {
+                block_start: Some(start),
+                block_end: Some((start + blocks_per_part - 1).min(block_end)),
+                ..self.clone()
+            })
+            .collect()
+    }
+}
+
+/// Scan results to persist on an event. Unset fields are left untouched.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventUpdate {
+    pub landed: Option<bool>,
+    pub landing: Option<Landing>,
+    /// Replaces all stored refunds of the event when set.
+    pub refunds: Option<Vec<Refund>>,
+    pub scan_status: Option<ScanStatus>,
+    pub disclosure_profile: Option<DisclosureProfile>,
+}
+
+/// Progress of a history sync over `block_start..=block_end` (or up to the latest block when
+/// `block_end` is `None`), persisted so an interrupted sync can resume where it stopped.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SyncCursor {
+    pub block_start: u64,
+    pub block_end: Option<u64>,
+    /// Number of events of the range already written to the store.
+    pub offset: u64,
+    /// `count` of the most recent history info response.
+    pub info_count: u64,
+}
+
+/// Outcome of [`EventStore::write_events`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteSummary {
+    /// Events that were not stored yet.
+    pub inserted: u64,
+    /// Events already stored under the same hint hash and block, left untouched.
+    pub existing: u64,
+}
+
+/// Storage backend for MEV-Share events.
+#[async_trait]
+pub trait EventStore: Clone + Send + Sync + 'static {
+    /// Writes `events` to the store, skipping events already stored under the same hint hash and block.
+    /// An event fetched from history replaces the copy of it received from the live stream, which
+    /// was stored under the block the node was at, and keeps its `received_at`.
+    async fn write_events(&self, events: Vec<Event>) -> Result<WriteSummary>;
+
+    /// Streams the events matching `filter`.
+    async fn read_events(&self, filter: &EventFilter) -> Result<EventStream<'static>>;
+
+    /// Applies `update` to the event with hint hash `hash` and returns the number of events modified.
+    async fn update_event(&self, hash: H256, update: EventUpdate) -> Result<u64>;
+
+    /// Counts the events matching `filter`.
+    async fn count(&self, filter: &EventFilter) -> Result<u64>;
+
+    /// Returns the lowest and highest `Event.block` among the events matching `filter`.
+    async fn block_range(&self, filter: &EventFilter) -> Result<Option<(u64, u64)>>;
+
+    /// Returns the sync cursor of the range `block_start..=block_end`, if one was written.
+    async fn read_sync_cursor(
+        &self,
+        block_start: u64,
+        block_end: Option<u64>,
+    ) -> Result<Option<SyncCursor>>;
+
+    /// Writes `cursor`, replacing any cursor of the same range.
+    async fn write_sync_cursor(&self, cursor: &SyncCursor) -> Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_blocks() {
+        let filter = EventFilter {
+            timestamp_start: Some(1),
+            ..Default::default()
+        };
+        let shards = filter.split_blocks(10, 20, 4);
+        let ranges: Vec<_> = shards
+            .iter()
+            .map(|shard| (shard.block_start.unwrap(), shard.block_end.unwrap()))
+            .collect();
+        assert_eq!(ranges, vec![(10, 12), (13, 15), (16, 18), (19, 20)]);
+        assert!(shards.iter().all(|shard| shard.timestamp_start == Some(1)));
+
+        assert_eq!(filter.split_blocks(5, 6, 8).len(), 2);
+        assert!(filter.split_blocks(6, 5, 4).is_empty());
+    }
+}
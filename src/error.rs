@@ -0,0 +1,65 @@
+use ethers::providers::ProviderError;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors returned by the library.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The Ethereum node returned an error.
+    #[error("rpc error: {0}")]
+    Rpc(#[from] ProviderError),
+    /// The event store returned an error.
+    #[error("store error: {0}")]
+    Store(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// The MEV-Share API returned an error.
+    #[error("api error: {0}")]
+    Api(#[from] reqwest::Error),
+    /// A value could not be encoded or decoded.
+    #[error("failed to decode {what}: {reason}")]
+    Decode { what: String, reason: String },
+    /// A field expected to be set was missing, e.g. the author of a block.
+    #[error("missing field `{0}`")]
+    MissingField(&'static str),
+    /// Reading or writing a local file failed.
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl Error {
+    pub fn decode(what: impl Into<String>, reason: impl std::fmt::Display) -> Self {
+        Error::Decode {
+            what: what.into(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl From<mongodb::error::Error> for Error {
+    fn from(error: mongodb::error::Error) -> Self {
+        Error::Store(Box::new(error))
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(error: rusqlite::Error) -> Self {
+        Error::Store(Box::new(error))
+    }
+}
+
+impl From<parquet::errors::ParquetError> for Error {
+    fn from(error: parquet::errors::ParquetError) -> Self {
+        Error::Store(Box::new(error))
+    }
+}
+
+impl From<arrow_schema::ArrowError> for Error {
+    fn from(error: arrow_schema::ArrowError) -> Self {
+        Error::Store(Box::new(error))
+    }
+}
+
+impl From<mongodb::bson::ser::Error> for Error {
+    fn from(error: mongodb::bson::ser::Error) -> Self {
+        Error::decode("bson document", error)
+    }
+}
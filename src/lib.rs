@@ -1,3 +1,7 @@
+pub mod analysis;
+pub mod api;
 pub mod cli;
+pub mod config;
 pub mod data;
+pub mod error;
 pub mod refunds;
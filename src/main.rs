@@ -1,152 +1,454 @@
 use dotenv::dotenv;
-use ethers::providers::{Http, JsonRpcClient, Provider};
-use futures::TryStreamExt;
-use mev_share::sse::{EventClient, EventHistory, EventHistoryInfo, EventHistoryParams};
+use ethers::providers::{Http, Ipc, JsonRpcClient, Provider, ProviderError};
+use ethers::types::{Block, Transaction, H256, U256};
+use futures::{StreamExt, TryStreamExt};
+use mev_share::sse::{EventClient, EventHistoryInfo, EventHistoryParams};
 use mev_share_analysis::{
-    cli::{Cli, Commands},
-    data::{event::Event, mongo::MongoClient},
-    refunds::{landing::Landing, refund::Refund},
+    analysis::{
+        builders::BuilderLeaderboard,
+        hints::HintReport,
+        profile::classify_events,
+        report::{render, ReportFormat, ReportRow},
+        stats::{BuilderFilter, Stats},
+        timeseries::TimeSeries,
+    },
+    api::HistoryClient,
+    cli::{Cli, Commands, ReportCommand},
+    config::Config,
+    data::{
+        event::{Event, ScanStatus, LANDING_WINDOW},
+        export::export_parquet,
+        mongo::MongoClient,
+        sqlite::SqliteStore,
+        store::{EventFilter, EventStore, EventUpdate, SyncCursor},
+    },
+    error::{Error, Result},
+    refunds::{
+        backrun::Backrun,
+        builder::BuilderRegistry,
+        chain::ChainClient,
+        landing::Landing,
+        refund::{Refund, RefundRecipients},
+    },
 };
-use mongodb::{
-    bson::{doc, to_document},
-    options::{FindOptions, Hint as IndexHint},
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread::available_parallelism,
+    time::Duration,
 };
-use std::{env, thread::available_parallelism};
+
+/// Interval at which `events --follow` polls the node for its latest block.
+const HEAD_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Totals of a scanning task.
+#[derive(Debug, Default)]
+struct ScanTotals {
+    landings: u64,
+    /// Landings whose transaction reverted.
+    reverted: u64,
+    /// Refunded wei.
+    refunded: U256,
+    refunds: u64,
+    errors: u64,
+    /// Events scanned.
+    iterations: u64,
+}
+
+impl ScanTotals {
+    fn add(&mut self, other: &ScanTotals) {
+        self.landings += other.landings;
+        self.reverted += other.reverted;
+        self.refunded = self.refunded.saturating_add(other.refunded);
+        self.refunds += other.refunds;
+        self.errors += other.errors;
+        self.iterations += other.iterations;
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenv().ok();
 
     // SETUP
-
-    // ENDPOINTS
-    const HISTORY: &str = "https://mev-share.flashbots.net/api/v1/history";
-    const HISTORY_INFO: &str = "https://mev-share.flashbots.net/api/v1/history/info";
-    let _rpc_url = env::var("RPC_URL").unwrap_or_else(|_| "http://localhost:8545".into());
-    const MONGO_URL: &str = "mongodb://localhost:27017";
-    // let ipc_path = "/tmp/reth.ipc"; // Use when you're running a local node
+    let cli = Cli::parse_args();
+    let config = cli.config()?;
 
     // PROVIDER
-    // let provider = Provider::connect_ipc(ipc_path).await.unwrap();
-    let provider = Provider::<Http>::try_from(_rpc_url).unwrap(); // Unused. Ideally use your own node if using the RPC.
-
-    // MONGO
-    const DB_NAME: &str = "mev-share-test";
-    const EVENTS_COLLECTION: &str = "events"; // Name of the collection to save the events in.
-    let mongo = MongoClient::new(MONGO_URL, DB_NAME, EVENTS_COLLECTION).await;
+    match &config.rpc.ipc_path {
+        // Use when you're running a local node
+        Some(ipc_path) => {
+            let provider = Provider::<Ipc>::connect_ipc(ipc_path).await?;
+            let chain = ChainClient::new(provider, config.rpc.block_cache_size);
+            with_store(cli.command, &config, &chain).await
+        }
+        None => {
+            let provider = Provider::<Http>::try_from(config.rpc.url.as_str())?; // Ideally use your own node if using the RPC.
+            let chain = ChainClient::new(provider, config.rpc.block_cache_size)
+                .with_batch_url(&config.rpc.url);
+            with_store(cli.command, &config, &chain).await
+        }
+    }
+}
 
+async fn with_store<T: JsonRpcClient + Clone + 'static>(
+    command: Option<Commands>,
+    config: &Config,
+    chain: &ChainClient<T>,
+) -> anyhow::Result<()> {
     let mev_share_client = EventClient::default();
-    let cli = Cli::parse_args();
+    match &config.store.sqlite {
+        // SQLITE
+        Some(path) => {
+            let sqlite = SqliteStore::open(path)?;
+            run(command, config, &sqlite, chain, &mev_share_client).await
+        }
+        // MONGO
+        None => {
+            let mongo = MongoClient::new(
+                &config.store.mongo_url,
+                &config.store.db_name,
+                &config.store.events_collection,
+            )
+            .await?
+            .with_sync_cursors_collection(&config.store.sync_cursors_collection)
+            .with_read_batch_size(config.store.read_batch_size);
+            run(command, config, &mongo, chain, &mev_share_client).await
+        }
+    }
+}
 
-    match cli.command {
+async fn run<T: JsonRpcClient + Clone + 'static, S: EventStore>(
+    command: Option<Commands>,
+    config: &Config,
+    store: &S,
+    chain: &ChainClient<T>,
+    mev_share_client: &EventClient,
+) -> anyhow::Result<()> {
+    match command {
         Some(Commands::Events {
             block_start,
             block_end,
+            from_scratch,
+            follow,
+            ..
         }) => {
-            let info = get_historical_info(&mev_share_client, HISTORY_INFO).await;
-            let offset = 0; // Offset the request by `offset` events.
-            println!(
-                "Fetching MEV-Share events from block {} to block {}",
-                block_start.unwrap_or(info.min_block),
-                block_end.unwrap_or(info.max_block)
-            );
+            let history_client = HistoryClient::new(&config.api);
+            let info = history_client.event_history_info().await?;
+            let mut block_start = block_start.unwrap_or(info.min_block);
             let start = std::time::Instant::now();
-            fetch_history(
-                &mev_share_client,
-                &mongo,
-                HISTORY,
-                info,
-                offset,
-                block_start,
-                block_end,
-            )
-            .await;
+            if config.history.workers > 1 {
+                let backfill_end = block_end.unwrap_or(info.max_block);
+                println!(
+                    "Backfilling MEV-Share events from block {} to block {} with {} workers",
+                    block_start, backfill_end, config.history.workers
+                );
+                backfill_history(
+                    &history_client,
+                    store,
+                    config,
+                    &info,
+                    (block_start, backfill_end),
+                    from_scratch,
+                )
+                .await?;
+                // Continue with new events after the backfilled range.
+                block_start = backfill_end + 1;
+            }
+            if block_end.is_none_or(|block_end| block_start <= block_end) {
+                // Offset the request by `offset` events, resuming from the persisted cursor if any.
+                let cursor = match from_scratch {
+                    true => None,
+                    false => store.read_sync_cursor(block_start, block_end).await?,
+                };
+                let offset = cursor.map_or(0, |cursor| cursor.offset);
+                println!(
+                    "Fetching MEV-Share events from block {} to block {}",
+                    block_start,
+                    block_end.unwrap_or(info.max_block)
+                );
+                if offset > 0 {
+                    println!("Resuming from offset {}", offset);
+                }
+                fetch_history(
+                    &history_client,
+                    store,
+                    config,
+                    info,
+                    offset,
+                    Some(block_start),
+                    block_end,
+                    follow,
+                )
+                .await?;
+            }
             let end = std::time::Instant::now();
             println!("Took {:?} to fetch events", end - start);
+            if history_client.stats().failures() > 0 {
+                println!("History API failures: {}", history_client.stats());
+            }
+
+            if follow {
+                println!("History synced, following live events...");
+                follow_stream(mev_share_client, store, chain, &config.api.stream).await?;
+            }
         }
-        Some(Commands::ScanRefunds) => {
+        Some(Commands::ScanRefunds {
+            range,
+            rescan,
+            by_block,
+            ..
+        }) => {
             println!("Retrieving refunds for events in db...");
-            // TODO: Add filter for querying by block in range `block_start : block_end` and using the index `block: 1`.
-            let available_cores = available_parallelism()
-                .unwrap_or(std::num::NonZeroUsize::try_from(4).unwrap())
-                .get() as u64; // Uses all cores if available.
-            let count = mongo.count().await; // Num. of documents in DB.
-
-            // TODO: Divide blocks_per_core instead of docs_per_core when querying by block.
-            let docs_per_core = (count - (count % available_cores)) / available_cores;
-
-            let mut skip_docs: u64 = 0;
-            let mut handlers = vec![];
-            for _i in 0..available_cores {
-                let index_hint = IndexHint::Keys(doc! {"hint.hash": 1});
-                let options = FindOptions::builder()
-                    .batch_size(10_000_000) // 10 million
-                    .allow_disk_use(true)
-                    .hint(index_hint)
-                    .skip(skip_docs)
-                    .limit(docs_per_core as i64)
-                    .build();
-                skip_docs += docs_per_core; // TODO: Reconfigure this when querying by block.
-                let mongo = mongo.clone();
-                let provider = provider.clone();
-                handlers.push(tokio::task::spawn(async move {
-                    check_landing_and_refund(&provider, options, &mongo).await
-                }));
-            }
+            let recipients = Arc::new(RefundRecipients::from_config(&config.scan)?);
+            let builders = Arc::new(match &config.scan.builders_file {
+                Some(path) => BuilderRegistry::load(path)?,
+                None => BuilderRegistry::default(),
+            });
+            let workers = config.scan.workers.unwrap_or_else(|| {
+                available_parallelism()
+                    .unwrap_or(std::num::NonZeroUsize::try_from(4).unwrap())
+                    .get() // Uses all cores if available.
+            }) as u64;
+            let filter = EventFilter {
+                unscanned: !rescan,
+                ..EventFilter::from(range)
+            };
+            let Some((first_block, last_block)) = store.block_range(&filter).await? else {
+                println!("No events in db left to scan");
+                return Ok(());
+            };
+            println!(
+                "Scanning events from block {} to block {}",
+                first_block, last_block
+            );
 
             let start = std::time::Instant::now();
-            let results = futures::future::join_all(handlers).await;
+            let mut results = vec![];
+            if by_block {
+                // Events can land up to `LANDING_WINDOW` blocks after their hint.
+                let walk_end = (last_block + LANDING_WINDOW).min(chain.get_block_number().await?);
+                if walk_end < first_block {
+                    println!(
+                        "Node is at block {}, behind the events from block {}",
+                        walk_end, first_block
+                    );
+                    return Ok(());
+                }
+                println!("Walking blocks {} to {}", first_block, walk_end);
+
+                // Each task walks its own sub-range of blocks.
+                let mut handlers = vec![];
+                for shard in filter.split_blocks(first_block, walk_end, workers) {
+                    let range = (shard.block_start.unwrap(), shard.block_end.unwrap());
+                    let (store, chain, filter) = (store.clone(), chain.clone(), filter.clone());
+                    let (recipients, builders) = (recipients.clone(), builders.clone());
+                    handlers.push(tokio::task::spawn(async move {
+                        scan_blocks(&chain, &filter, &store, &recipients, &builders, range).await
+                    }));
+                }
+                let mut found = HashSet::new();
+                let mut complete = true;
+                for result in futures::future::join_all(handlers).await {
+                    results.push(result?.map(|(totals, landed)| {
+                        found.extend(landed);
+                        totals
+                    }));
+                    complete &= results.last().unwrap().is_ok();
+                }
+                // Without every block walked, an event missing from `found` may still have landed.
+                if complete {
+                    results.push(mark_not_landed(&filter, store, &found, walk_end).await);
+                }
+            } else {
+                // Each task scans its own sub-range of blocks.
+                let mut handlers = vec![];
+                for shard in filter.split_blocks(first_block, last_block, workers) {
+                    let store = store.clone();
+                    let chain = chain.clone();
+                    let (recipients, builders) = (recipients.clone(), builders.clone());
+                    let batch_size = config.rpc.batch_size;
+                    handlers.push(tokio::task::spawn(async move {
+                        check_landing_and_refund(
+                            &chain,
+                            &shard,
+                            &store,
+                            &recipients,
+                            &builders,
+                            batch_size,
+                        )
+                        .await
+                    }));
+                }
+                for result in futures::future::join_all(handlers).await {
+                    results.push(result?);
+                }
+            }
             let end = std::time::Instant::now();
 
-            let mut total_landings = 0;
-            let mut total_refunded = 0;
-            let mut total_refunds = 0;
-            let mut total_iterations = 0;
+            let mut total = ScanTotals::default();
             for result in results {
-                let (landings, refunded, refunds, iterations) = result.unwrap();
-                total_landings += landings;
-                total_refunded += refunded;
-                total_refunds += refunds;
-                total_iterations += iterations;
+                match result {
+                    Ok(totals) => total.add(&totals),
+                    // The events this task did not reach stay unscanned for the next run.
+                    Err(error) => println!("Scan task failed: {}", error),
+                }
             }
 
-            println!("Took {:?} to scan {} events", end - start, total_iterations);
+            println!("Took {:?} to scan {} events", end - start, total.iterations);
+            if total.errors > 0 {
+                println!(
+                    "{} events failed to scan and will be retried on the next run",
+                    total.errors
+                );
+            }
+            println!(
+                "Total landings: {} ({} reverted) | Total refunds: {} | Total refunded: {} wei",
+                total.landings, total.reverted, total.refunds, total.refunded
+            );
+        }
+        Some(Commands::Export {
+            block_start,
+            block_end,
+            out_dir,
+            partition_size,
+            batch_size,
+        }) => {
+            println!("Exporting events in db to {}...", out_dir.display());
+            let start = std::time::Instant::now();
+            let summary = export_parquet(
+                store,
+                &out_dir,
+                block_start,
+                block_end,
+                partition_size,
+                batch_size as usize,
+            )
+            .await?;
+            let end = std::time::Instant::now();
             println!(
-                "Total landings: {} | Total refunds: {} | Total refunded: {} wei",
-                total_landings, total_refunds, total_refunded
+                "Took {:?} to export {} events ({} hint txs, {} hint logs) in {} partitions",
+                end - start,
+                summary.events,
+                summary.txs,
+                summary.logs,
+                summary.partitions
             );
         }
+        Some(Commands::Classify { range, reclassify }) => {
+            println!("Classifying hint disclosure profiles of events in db...");
+            let filter = EventFilter {
+                unclassified: !reclassify,
+                ..EventFilter::from(range)
+            };
+            let start = std::time::Instant::now();
+            let classified = classify_events(store, &filter).await?;
+            let end = std::time::Instant::now();
+            println!("Took {:?} to classify {} events", end - start, classified);
+        }
+        Some(Commands::Stats {
+            range,
+            builder,
+            format,
+        }) => {
+            let filter = EventFilter::from(range);
+            let builder = builder.as_deref().map(BuilderFilter::new);
+            let mut stats = Stats::default();
+            for_each_event(store, &filter, |event| {
+                if builder
+                    .as_ref()
+                    .is_none_or(|builder| builder.matches(event))
+                {
+                    stats.add(event);
+                }
+            })
+            .await?;
+            print_report(&[stats], format)?;
+        }
+        Some(Commands::Report {
+            report: ReportCommand::Hints { range, format },
+        }) => {
+            let filter = EventFilter::from(range);
+            let mut report = HintReport::default();
+            for_each_event(store, &filter, |event| report.add(event)).await?;
+            print_report(&report.rows(), format)?;
+        }
+        Some(Commands::Report {
+            report:
+                ReportCommand::Builders {
+                    range,
+                    sort_by,
+                    format,
+                },
+        }) => {
+            let filter = EventFilter::from(range);
+            let mut leaderboard = BuilderLeaderboard::default();
+            for_each_event(store, &filter, |event| leaderboard.add(event)).await?;
+            print_report(&leaderboard.rows(sort_by), format)?;
+        }
+        Some(Commands::Report {
+            report:
+                ReportCommand::Timeseries {
+                    range,
+                    interval,
+                    format,
+                },
+        }) => {
+            let filter = EventFilter::from(range);
+            let mut series = TimeSeries::new(interval);
+            for_each_event(store, &filter, |event| series.add(event)).await?;
+            print_report(&series.rows(), format)?;
+        }
         None => {
             println!("No command provided");
         }
     }
-
     Ok(())
 }
 
-async fn get_historical_info(client: &EventClient, endpoint: &str) -> EventHistoryInfo {
-    client
-        .event_history_info(endpoint)
-        .await
-        .expect("Failed to get historical info")
+/// Calls `f` with every event matching `filter`. Events that fail to read are skipped and
+/// reported on stderr, so they do not mix with report output.
+async fn for_each_event<S: EventStore>(
+    store: &S,
+    filter: &EventFilter,
+    mut f: impl FnMut(&Event),
+) -> Result<()> {
+    let mut events = store.read_events(filter).await?;
+    let mut errors = 0;
+    while let Some(event) = events.next().await {
+        match event {
+            Ok(event) => f(&event),
+            Err(error) => {
+                eprintln!("Failed to read event: {}", error);
+                errors += 1;
+            }
+        }
+    }
+    if errors > 0 {
+        eprintln!("Skipped {} events that failed to read", errors);
+    }
+    Ok(())
 }
 
-async fn get_historical_events(
-    client: &EventClient,
-    endpoint: &str,
-    params: &EventHistoryParams,
-) -> Result<Vec<EventHistory>, reqwest::Error> {
-    client.event_history(endpoint, params.clone()).await
+fn print_report<R: ReportRow>(rows: &[R], format: ReportFormat) -> Result<()> {
+    println!("{}", render(rows, format)?);
+    Ok(())
 }
 
-async fn fetch_history(
-    client: &EventClient,
-    mongo: &MongoClient,
-    endpoint: &str,
+#[allow(clippy::too_many_arguments)]
+async fn fetch_history<S: EventStore>(
+    client: &HistoryClient,
+    store: &S,
+    config: &Config,
     info: EventHistoryInfo,
     offset: u64,
     block_start: Option<u64>,
     block_end: Option<u64>,
-) {
+    follow: bool,
+) -> anyhow::Result<()> {
     // Set Initial Params
     let mut params = EventHistoryParams {
         block_start: if block_start.is_some() {
@@ -161,103 +463,398 @@ async fn fetch_history(
         },
         timestamp_start: None,
         timestamp_end: None,
-        limit: Some(info.max_limit),
+        limit: Some(page_limit(config, &info)),
         offset: Some(offset),
     };
     let mut sync_complete = false;
+    let mut info_count = info.count;
     loop {
-        // Get Historical Events
-        match get_historical_events(client, endpoint, &params).await {
-            Ok(events) => {
-                let mut next_offset = params.offset.unwrap();
-                if events.is_empty() {
-                    // Fetch new info
-                    let new_info = get_historical_info(
-                        client,
-                        "https://mev-share.flashbots.net/api/v1/history/info",
-                    )
-                    .await;
-
-                    if block_end.is_some() && block_end.unwrap() < new_info.max_block {
-                        println!("Fetched events till block {}", block_end.unwrap());
-                        println!("Exiting...");
-                        break;
-                    }
-                    // Update Params
-                    params.block_end = Some(new_info.max_block);
-                    params.limit = Some(new_info.max_limit);
-                    params.offset = Some(next_offset);
-                    println!("Sleeping for 12 seconds...waiting for events to be indexed");
-                    std::thread::sleep(std::time::Duration::from_secs(12));
-                } else {
-                    // Map the incoming events<EventHistory> to events<Event>
-                    let events: Vec<Event> = events.into_iter().map(Event::new).collect();
-                    // Write Events to DB
-                    let num_events_written = mongo.write_events(events).await;
-
-                    // Update Params
-                    next_offset = params.offset.unwrap() + num_events_written;
-                    params.offset = Some(next_offset);
-
-                    // Check if Sync Complete
-                    if next_offset >= info.count && !sync_complete {
-                        println!("Sync Complete!");
-                        sync_complete = true;
-                    }
+        // Get Historical Events. Failed requests are retried by the client, so an error here
+        // means the API kept failing; the sync cursor lets the next run resume.
+        let events = client.event_history(&params).await?;
+        let mut next_offset = params.offset.unwrap();
+        if events.is_empty() {
+            // Fetch new info
+            let new_info = client.event_history_info().await?;
+
+            if let Some(block_end) = block_end.filter(|end| *end < new_info.max_block) {
+                println!("Fetched events till block {}", block_end);
+                println!("Exiting...");
+                break;
+            }
+            if follow {
+                // Caught up with history, new events are taken from the live stream.
+                break;
+            }
+            // Update Params
+            info_count = new_info.count;
+            params.block_end = Some(new_info.max_block);
+            params.limit = Some(page_limit(config, &new_info));
+            params.offset = Some(next_offset);
+            println!("Sleeping for 12 seconds...waiting for events to be indexed");
+            tokio::time::sleep(std::time::Duration::from_secs(12)).await;
+        } else {
+            // Map the incoming events<EventHistory> to events<Event>
+            let events: Vec<Event> = events.into_iter().map(Event::new).collect();
+            // Write Events to DB
+            let summary = store.write_events(events).await?;
+            if summary.existing > 0 {
+                println!(
+                    "Inserted {} events, {} already present",
+                    summary.inserted, summary.existing
+                );
+            }
+
+            // Update Params
+            next_offset = params.offset.unwrap() + summary.inserted + summary.existing;
+            params.offset = Some(next_offset);
+
+            // Persist Progress
+            let cursor = SyncCursor {
+                block_start: params.block_start.unwrap(),
+                block_end,
+                offset: next_offset,
+                info_count,
+            };
+            store.write_sync_cursor(&cursor).await?;
+
+            // Check if Sync Complete
+            if next_offset >= info.count && !sync_complete {
+                println!("Sync Complete!");
+                sync_complete = true;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Number of events to request per history page.
+fn page_limit(config: &Config, info: &EventHistoryInfo) -> u64 {
+    config
+        .history
+        .page_size
+        .map_or(info.max_limit, |page_size| page_size.min(info.max_limit))
+}
+
+/// Fetches the blocks in `range` as chunks of `config.history.chunk_size` blocks, with up to
+/// `config.history.workers` chunks in flight. Each chunk keeps its own sync cursor, so an
+/// interrupted backfill resumes per chunk.
+async fn backfill_history<S: EventStore>(
+    client: &HistoryClient,
+    store: &S,
+    config: &Config,
+    info: &EventHistoryInfo,
+    (block_start, block_end): (u64, u64),
+    from_scratch: bool,
+) -> anyhow::Result<()> {
+    let chunk_size = config.history.chunk_size;
+    let chunks = (block_start..=block_end)
+        .step_by(chunk_size as usize)
+        .map(|start| Ok((start, (start + chunk_size - 1).min(block_end))));
+    futures::stream::iter(chunks)
+        .try_for_each_concurrent(
+            config.history.workers,
+            |(chunk_start, chunk_end)| async move {
+                let cursor = match from_scratch {
+                    true => None,
+                    false => store.read_sync_cursor(chunk_start, Some(chunk_end)).await?,
+                };
+                let offset = cursor.map_or(0, |cursor| cursor.offset);
+                fetch_history(
+                    client,
+                    store,
+                    config,
+                    info.clone(),
+                    offset,
+                    Some(chunk_start),
+                    Some(chunk_end),
+                    false,
+                )
+                .await
+            },
+        )
+        .await
+}
+
+/// Writes the events of the live SSE stream to `store` as they are received, under the latest
+/// block of the node. The block is polled in the background so writes never wait on the node.
+async fn follow_stream<T: JsonRpcClient + Clone + 'static, S: EventStore>(
+    client: &EventClient,
+    store: &S,
+    chain: &ChainClient<T>,
+    endpoint: &str,
+) -> anyhow::Result<()> {
+    let mut stream = client.events(endpoint).await?;
+    // Until the node answers, events are stored under the latest block already in the store.
+    let last_stored = store.block_range(&EventFilter::default()).await?;
+    let head = Arc::new(AtomicU64::new(last_stored.map_or(0, |(_, last)| last)));
+    let tracker = tokio::task::spawn(track_head(chain.clone(), head.clone()));
+    loop {
+        match stream.next().await {
+            Some(Ok(event)) => {
+                let received_at = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_millis() as u64;
+                let event = Event::from_stream(event, head.load(Ordering::Relaxed), received_at);
+                if let Err(error) = store.write_events(vec![event]).await {
+                    println!("Failed to store event: {}", error);
                 }
             }
-            Err(_error) => {
-                // @Dev Occasionally, the MEV-Share API will return an error. We do not update the offset and retry in the next iteration.
+            Some(Err(error)) => {
+                println!("Event stream error: {}", error);
+            }
+            None => {
+                // @Dev The stream ends when the connection drops. Reconnect and keep following.
+                println!("Event stream closed, reconnecting...");
+                if let Err(error) = stream.retry().await {
+                    println!("Failed to reconnect to event stream: {}", error);
+                    break;
+                }
             }
         }
     }
+    tracker.abort();
+    Ok(())
 }
 
-async fn check_landing_and_refund<T: JsonRpcClient>(
-    provider: &Provider<T>,
-    find_options: FindOptions,
-    mongo: &MongoClient,
-) -> (u64, u128, u64, u64) {
+/// Polls the latest block of the node into `head` every [`HEAD_POLL_INTERVAL`]. The last known
+/// block is kept while the node is unavailable.
+async fn track_head<T: JsonRpcClient>(chain: ChainClient<T>, head: Arc<AtomicU64>) {
+    let mut interval = tokio::time::interval(HEAD_POLL_INTERVAL);
+    let mut available = true;
+    loop {
+        interval.tick().await;
+        match chain.get_block_number().await {
+            Ok(block) => {
+                head.store(block, Ordering::Relaxed);
+                available = true;
+            }
+            // Only report the first failure of an outage.
+            Err(error) if available => {
+                println!("Failed to get block number: {}", error);
+                available = false;
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// Scans the events matching `filter`, looking up the transactions of `batch_size` events at once.
+async fn check_landing_and_refund<T: JsonRpcClient, S: EventStore>(
+    chain: &ChainClient<T>,
+    filter: &EventFilter,
+    store: &S,
+    recipients: &RefundRecipients,
+    builders: &BuilderRegistry,
+    batch_size: usize,
+) -> Result<ScanTotals> {
+    // Events not found onchain are marked as not landed as of this block.
+    let latest_block = chain.get_block_number().await?;
+
     // Read Events from DB using cursor
-    let mut cursor = mongo.read_events(None, Some(find_options)).await;
-
-    let mut iterations = 0;
-    let mut total_refunded: u128 = 0;
-    let mut total_landings: u64 = 0;
-    let mut total_refunds: u64 = 0;
-    while let Some(event_doc) = cursor.try_next().await.unwrap() {
-        // Check if event landed onchain using hint.hash
-        let hash = event_doc.hint.hash;
-        let (landing, target_tx, landing_block) = Landing::get_landing_for_tx(hash, provider).await;
-
-        match landing {
-            Some(landing) => {
-                // Check if refund txn exists
-                let target_txn = target_tx.unwrap();
-                let block = landing_block.unwrap();
-                total_landings += 1;
-                let refund = Refund::scan_refund(&target_txn, &block, provider).await;
-
-                match refund {
-                    Some(refund) => {
-                        // Update event in DB with landed: true and refund params
-                        let update = doc! {"$set": doc! {"landed": true, "landing": to_document(&landing).unwrap(), "refund": to_document(&refund).unwrap()}};
-                        mongo.update_event(hash, update).await;
-                        total_refunds += 1;
-                        total_refunded += refund.value as u128;
-                    }
-                    None => {
-                        // Update event in DB with landed: true and landing parameters.
-                        let update = doc! {"$set": doc! {"landed": true, "landing": to_document(&landing).unwrap()}};
-                        mongo.update_event(hash, update).await;
-                    }
+    let mut cursor = store.read_events(filter).await?.chunks(batch_size.max(1));
+
+    let mut totals = ScanTotals::default();
+    while let Some(event_docs) = cursor.next().await {
+        let mut hashes = vec![];
+        for event_doc in event_docs {
+            match event_doc {
+                Ok(event_doc) => hashes.push(event_doc.hint.hash),
+                Err(error) => {
+                    println!("Failed to read event: {}", error);
+                    totals.errors += 1;
                 }
             }
-            None => {
-                // Do nothing
+        }
+        // Check if events landed onchain using hint.hash
+        let txs = match chain.get_transactions(&hashes).await {
+            Ok(txs) => txs,
+            Err(error) => {
+                // Record the failure on every event of the batch; they are retried next run.
+                let message = error.to_string();
+                hashes
+                    .iter()
+                    .map(|_| Err(Error::Rpc(ProviderError::CustomError(message.clone()))))
+                    .collect()
+            }
+        };
+        for (hash, tx) in hashes.into_iter().zip(txs) {
+            let landing = match tx {
+                Ok(tx) => Landing::get_landing(tx, chain, recipients).await,
+                Err(error) => Err(error),
+            };
+            let update = match landing {
+                Ok((Some(landing), Some(target_txn), Some(block))) => landed_update(
+                    landing,
+                    &target_txn,
+                    &block,
+                    recipients,
+                    builders,
+                    &mut totals,
+                ),
+                Ok(_) => EventUpdate {
+                    landed: Some(false),
+                    scan_status: Some(ScanStatus::NotLanded {
+                        as_of_block: latest_block,
+                    }),
+                    ..Default::default()
+                },
+                Err(error) => error_update(error, &mut totals),
+            };
+            store.update_event(hash, update).await?;
+            totals.iterations += 1;
+        }
+    }
+    Ok(totals)
+}
+
+/// Walks blocks `block_start..=block_end` and scans the stored events matching `filter` whose
+/// hint hash is a transaction of the block. Returns the hashes of the events found.
+async fn scan_blocks<T: JsonRpcClient, S: EventStore>(
+    chain: &ChainClient<T>,
+    filter: &EventFilter,
+    store: &S,
+    recipients: &RefundRecipients,
+    builders: &BuilderRegistry,
+    (block_start, block_end): (u64, u64),
+) -> Result<(ScanTotals, HashSet<H256>)> {
+    let mut totals = ScanTotals::default();
+    let mut found = HashSet::new();
+    for number in block_start..=block_end {
+        let block = chain.get_block(number).await?;
+        if block.transactions.is_empty() {
+            continue;
+        }
+        let filter = EventFilter {
+            hashes: Some(block.transactions.iter().map(|tx| tx.hash).collect()),
+            ..filter.clone()
+        };
+        let events: Vec<_> = store.read_events(&filter).await?.collect().await;
+        let mut landed = vec![];
+        for event in events {
+            match event {
+                Ok(event) => landed.push(event.hint.hash),
+                Err(error) => {
+                    println!("Failed to read event: {}", error);
+                    totals.errors += 1;
+                }
+            }
+        }
+        if landed.is_empty() {
+            continue;
+        }
+        // One receipts request covers every landed event of the block.
+        let receipts = chain.get_block_receipts(number).await;
+        for hash in landed {
+            let Some(tx) = block.transactions.iter().find(|tx| tx.hash == hash) else {
+                continue;
+            };
+            let landing = match &receipts {
+                Ok(receipts) => receipts
+                    .iter()
+                    .find(|receipt| receipt.transaction_hash == hash)
+                    .ok_or(Error::MissingField("receipt"))
+                    .and_then(|receipt| {
+                        Ok(Landing {
+                            backruns: Backrun::from_receipts(tx, &block, recipients, receipts)?,
+                            ..Landing::new(tx, &block, receipt)?
+                        })
+                    }),
+                Err(error) => Err(Error::Rpc(ProviderError::CustomError(error.to_string()))),
+            };
+            let update = match landing {
+                Ok(landing) => {
+                    landed_update(landing, tx, &block, recipients, builders, &mut totals)
+                }
+                Err(error) => error_update(error, &mut totals),
+            };
+            store.update_event(hash, update).await?;
+            found.insert(hash);
+            totals.iterations += 1;
+        }
+    }
+    Ok((totals, found))
+}
+
+/// Marks the events matching `filter` that are not in `found` as not landed as of `as_of_block`.
+async fn mark_not_landed<S: EventStore>(
+    filter: &EventFilter,
+    store: &S,
+    found: &HashSet<H256>,
+    as_of_block: u64,
+) -> Result<ScanTotals> {
+    let mut totals = ScanTotals::default();
+    let mut events = store.read_events(filter).await?;
+    while let Some(event) = events.next().await {
+        let hash = match event {
+            Ok(event) => event.hint.hash,
+            Err(error) => {
+                println!("Failed to read event: {}", error);
+                totals.errors += 1;
+                continue;
+            }
+        };
+        if found.contains(&hash) {
+            continue;
+        }
+        let update = EventUpdate {
+            landed: Some(false),
+            scan_status: Some(ScanStatus::NotLanded { as_of_block }),
+            ..Default::default()
+        };
+        store.update_event(hash, update).await?;
+        totals.iterations += 1;
+    }
+    Ok(totals)
+}
+
+/// Builds the update of an event whose transaction `tx` landed in `block`.
+fn landed_update(
+    mut landing: Landing,
+    tx: &Transaction,
+    block: &Block<Transaction>,
+    recipients: &RefundRecipients,
+    builders: &BuilderRegistry,
+    totals: &mut ScanTotals,
+) -> EventUpdate {
+    landing.builder_name = builders.resolve(block).map(str::to_string);
+    totals.landings += 1;
+    if landing.success == Some(false) {
+        totals.reverted += 1;
+    }
+
+    // Check if refund txns exist
+    match Refund::scan_refunds(tx, block, recipients) {
+        Ok(refunds) => {
+            // Update event in DB with landed: true, landing and refund params
+            totals.refunds += refunds.len() as u64;
+            for refund in &refunds {
+                totals.refunded = totals.refunded.saturating_add(refund.value);
+            }
+            let scan_status = if refunds.is_empty() {
+                ScanStatus::Landed
+            } else {
+                ScanStatus::Refunded
+            };
+            EventUpdate {
+                landed: Some(true),
+                landing: Some(landing),
+                refunds: Some(refunds),
+                scan_status: Some(scan_status),
+                ..Default::default()
             }
         }
-        iterations += 1;
+        Err(error) => error_update(error, totals),
+    }
+}
+
+fn error_update(error: Error, totals: &mut ScanTotals) -> EventUpdate {
+    totals.errors += 1;
+    EventUpdate {
+        scan_status: Some(ScanStatus::Error {
+            message: error.to_string(),
+        }),
+        ..Default::default()
     }
-    (total_landings, total_refunded, total_refunds, iterations)
 }
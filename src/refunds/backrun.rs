@@ -0,0 +1,201 @@
+use super::{
+    chain::ChainClient,
+    refund::{decimal, Refund, RefundRecipients},
+};
+use crate::error::{Error, Result};
+use ethers::providers::JsonRpcClient;
+use ethers::types::{Address, Block, Transaction, TransactionReceipt, H256, U256};
+use serde::{Deserialize, Serialize};
+
+/// Searcher transaction that backran a landed MEV-Share transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Backrun {
+    pub hash: H256,
+    /// Searcher account that sent the backrun.
+    pub from: Address,
+    pub gas_used: Option<u64>,
+    /// Wei paid to the block builder: the priority fee plus any value sent directly to it.
+    /// Transfers to the builder from inside contract calls need traces and are not counted.
+    #[serde(with = "decimal")]
+    pub coinbase_payment: U256,
+}
+
+impl Backrun {
+    /// Transactions of `block` that backran `tx`: the ones directly after it, up to the first one
+    /// sent by the builder or by the sender of `tx`. Bundle boundaries are not visible onchain, so
+    /// the run only counts as backruns when it ends with a refund of `tx` (see
+    /// [`Refund::scan_refunds`]); otherwise there are none.
+    pub fn candidates<'a>(
+        tx: &Transaction,
+        block: &'a Block<Transaction>,
+        recipients: &RefundRecipients,
+    ) -> Result<&'a [Transaction]> {
+        let txn_index = tx
+            .transaction_index
+            .ok_or(Error::MissingField("transaction.transactionIndex"))?
+            .as_usize();
+        let builder = block.author.ok_or(Error::MissingField("block.author"))?;
+        let txns = block.transactions.get(txn_index + 1..).unwrap_or_default();
+        let end = txns
+            .iter()
+            .position(|txn| txn.from == builder || txn.from == tx.from);
+        match end {
+            Some(end) if Refund::paid_by(&txns[end], tx, builder, recipients).is_some() => {
+                Ok(&txns[..end])
+            }
+            _ => Ok(&[]),
+        }
+    }
+
+    /// Backrun `txn` of `block`, with its gas and fees from `receipt`.
+    pub fn new(
+        txn: &Transaction,
+        block: &Block<Transaction>,
+        receipt: &TransactionReceipt,
+    ) -> Result<Backrun> {
+        let gas_used = receipt
+            .gas_used
+            .map(|gas| u64::try_from(gas).map_err(|error| Error::decode("gas used", error)))
+            .transpose()?;
+        let gas_price = receipt
+            .effective_gas_price
+            .or(txn.gas_price)
+            .unwrap_or_default();
+        // Before London the whole gas price went to the builder.
+        let priority_fee = gas_price.saturating_sub(block.base_fee_per_gas.unwrap_or_default());
+        let mut coinbase_payment =
+            priority_fee.saturating_mul(receipt.gas_used.unwrap_or_default());
+        if txn.to.is_some() && txn.to == block.author {
+            coinbase_payment = coinbase_payment.saturating_add(txn.value);
+        }
+        Ok(Backrun {
+            hash: txn.hash,
+            from: txn.from,
+            gas_used,
+            coinbase_payment,
+        })
+    }
+
+    /// Backruns of `tx` in `block`, with the receipts of all transactions of the block.
+    pub fn from_receipts(
+        tx: &Transaction,
+        block: &Block<Transaction>,
+        recipients: &RefundRecipients,
+        receipts: &[TransactionReceipt],
+    ) -> Result<Vec<Backrun>> {
+        Self::candidates(tx, block, recipients)?
+            .iter()
+            .map(|txn| {
+                let receipt = receipts
+                    .iter()
+                    .find(|receipt| receipt.transaction_hash == txn.hash)
+                    .ok_or(Error::MissingField("receipt"))?;
+                Self::new(txn, block, receipt)
+            })
+            .collect()
+    }
+
+    /// Backruns of `tx` in `block`, fetching the receipt of each one.
+    pub async fn get_backruns<T: JsonRpcClient>(
+        tx: &Transaction,
+        block: &Block<Transaction>,
+        recipients: &RefundRecipients,
+        chain: &ChainClient<T>,
+    ) -> Result<Vec<Backrun>> {
+        let mut backruns = vec![];
+        for txn in Self::candidates(tx, block, recipients)? {
+            let receipt = chain
+                .get_receipt(txn.hash)
+                .await?
+                .ok_or(Error::MissingField("receipt"))?;
+            backruns.push(Self::new(txn, block, &receipt)?);
+        }
+        Ok(backruns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::refunds::test_tx;
+    use ethers::types::H160;
+
+    #[test]
+    fn test_backruns() {
+        let (user, builder, searcher) = (H160::random(), H160::random(), H160::random());
+        let target = test_tx(0, user, H160::random());
+        let backruns = [
+            test_tx(1, searcher, H160::random()),
+            test_tx(2, searcher, builder),
+        ];
+        let block = Block {
+            author: Some(builder),
+            base_fee_per_gas: Some(U256::from(10)),
+            transactions: vec![
+                target.clone(),
+                backruns[0].clone(),
+                backruns[1].clone(),
+                test_tx(3, builder, user),
+                test_tx(4, searcher, H160::random()),
+            ],
+            ..Default::default()
+        };
+        let receipts: Vec<_> = block
+            .transactions
+            .iter()
+            .map(|txn| TransactionReceipt {
+                transaction_hash: txn.hash,
+                gas_used: Some(U256::from(100)),
+                effective_gas_price: Some(U256::from(12)),
+                ..Default::default()
+            })
+            .collect();
+
+        let recipients = RefundRecipients::default();
+        let found = Backrun::from_receipts(&target, &block, &recipients, &receipts).unwrap();
+        assert_eq!(
+            found,
+            vec![
+                Backrun {
+                    hash: backruns[0].hash,
+                    from: searcher,
+                    gas_used: Some(100),
+                    coinbase_payment: U256::from(200),
+                },
+                Backrun {
+                    hash: backruns[1].hash,
+                    from: searcher,
+                    gas_used: Some(100),
+                    // Priority fees plus the direct transfer to the builder.
+                    coinbase_payment: U256::from(1_200),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_backruns_without_refund() {
+        let (user, builder) = (H160::random(), H160::random());
+        let target = test_tx(0, user, H160::random());
+        // The only builder transaction is the proposer payment at the end of the block.
+        let block = Block {
+            author: Some(builder),
+            transactions: vec![
+                target.clone(),
+                test_tx(1, H160::random(), H160::random()),
+                test_tx(2, H160::random(), H160::random()),
+                test_tx(3, builder, H160::random()),
+            ],
+            ..Default::default()
+        };
+
+        let recipients = RefundRecipients::default();
+        assert!(Backrun::candidates(&target, &block, &recipients)
+            .unwrap()
+            .is_empty());
+        assert!(Backrun::from_receipts(&target, &block, &recipients, &[])
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+use crate::error::{Error, Result};
+use ethers::types::{Address, Block, Transaction, H256};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Known block builder, identified by the fee recipients it builds blocks with or by patterns of
+/// the `extraData` it sets in their headers.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuilderEntry {
+    pub name: String,
+    #[serde(default)]
+    pub fee_recipients: Vec<Address>,
+    /// Case-insensitive substrings of the block `extraData`, decoded as UTF-8.
+    #[serde(default)]
+    pub extra_data: Vec<String>,
+}
+
+/// Resolves the builder of a block to a name. Fee recipients take precedence over `extraData`
+/// patterns, and earlier entries over later ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuilderRegistry {
+    builders: Vec<BuilderEntry>,
+}
+
+impl Default for BuilderRegistry {
+    /// Registry of a few well-known builders.
+    fn default() -> Self {
+        let entry = |name: &str, fee_recipient: &str, extra_data: &[&str]| BuilderEntry {
+            name: name.into(),
+            fee_recipients: vec![fee_recipient.parse().unwrap()],
+            extra_data: extra_data
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect(),
+        };
+        BuilderRegistry {
+            builders: vec![
+                entry(
+                    "flashbots",
+                    "0xdafea492d9c6733ae3d56b7ed1adb60692c98bc5",
+                    &["Illuminate Dmocratize Dstribute"],
+                ),
+                entry(
+                    "beaverbuild",
+                    "0x95222290dd7278aa3ddd389cc1e1d165cc4bafe5",
+                    &["beaverbuild"],
+                ),
+                entry(
+                    "titan",
+                    "0x4838b106fce9647bdf1e7877bf73ce8b0bad5f97",
+                    &["titanbuilder"],
+                ),
+                entry(
+                    "rsync",
+                    "0x1f9090aae28b8a3dceadf281b0f12828e676c326",
+                    &["rsync-builder"],
+                ),
+            ],
+        }
+    }
+}
+
+impl BuilderRegistry {
+    pub fn new(builders: Vec<BuilderEntry>) -> Self {
+        BuilderRegistry { builders }
+    }
+
+    /// Reads a registry from a JSON file holding a list of [`BuilderEntry`] objects, e.g.
+    /// `[{"name": "flashbots", "fee_recipients": ["0x..."], "extra_data": ["Illuminate"]}]`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let builders = serde_json::from_str(&contents)
+            .map_err(|error| Error::decode(path.display().to_string(), error))?;
+        Ok(Self::new(builders))
+    }
+
+    /// Name of the builder of `block`, if it is known.
+    pub fn resolve<T>(&self, block: &Block<T>) -> Option<&str> {
+        let by_fee_recipient = block.author.and_then(|author| {
+            self.builders
+                .iter()
+                .find(|builder| builder.fee_recipients.contains(&author))
+        });
+        let by_extra_data = || {
+            let extra_data = String::from_utf8_lossy(&block.extra_data).to_lowercase();
+            self.builders.iter().find(|builder| {
+                builder
+                    .extra_data
+                    .iter()
+                    .any(|pattern| extra_data.contains(&pattern.to_lowercase()))
+            })
+        };
+        by_fee_recipient
+            .or_else(by_extra_data)
+            .map(|builder| builder.name.as_str())
+    }
+}
+
+/// Transaction of `block` paying its proposer: by convention the last one, sent by the fee
+/// recipient of the block to the proposer's address.
+pub fn proposer_payment(block: &Block<Transaction>) -> Option<H256> {
+    let last = block.transactions.last()?;
+    let author = block.author?;
+    (last.from == author && last.to.is_some_and(|to| to != author)).then_some(last.hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::Bytes;
+
+    #[test]
+    fn test_resolve_builder() {
+        let registry = BuilderRegistry::default();
+        let block = |author: Address, extra_data: &str| Block::<Transaction> {
+            author: Some(author),
+            extra_data: Bytes::from(extra_data.as_bytes().to_vec()),
+            ..Default::default()
+        };
+        let flashbots = "0xdafea492d9c6733ae3d56b7ed1adb60692c98bc5"
+            .parse()
+            .unwrap();
+        assert_eq!(registry.resolve(&block(flashbots, "")), Some("flashbots"));
+        assert_eq!(
+            registry.resolve(&block(Address::random(), "Titanbuilder.xyz")),
+            Some("titan")
+        );
+        assert_eq!(registry.resolve(&block(Address::random(), "geth")), None);
+    }
+
+    #[test]
+    fn test_proposer_payment() {
+        let (builder, proposer) = (Address::random(), Address::random());
+        let tx = |from, to| Transaction {
+            hash: H256::random(),
+            from,
+            to: Some(to),
+            ..Default::default()
+        };
+        let payment = tx(builder, proposer);
+        let mut block = Block {
+            author: Some(builder),
+            transactions: vec![tx(Address::random(), builder), payment.clone()],
+            ..Default::default()
+        };
+        assert_eq!(proposer_payment(&block), Some(payment.hash));
+
+        block.transactions.pop();
+        assert_eq!(proposer_payment(&block), None);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::error::{Error, Result};
+use ethers::providers::{JsonRpcClient, Middleware, Provider, ProviderError};
+use ethers::types::{Block, Transaction, TransactionReceipt, H256};
+use hashlink::LruCache;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+/// Ethereum node client of the refund scanner. Full blocks are cached by number, and transaction
+/// lookups are sent as JSON-RPC batches when a batch endpoint is set.
+#[derive(Clone)]
+pub struct ChainClient<T> {
+    provider: Provider<T>,
+    /// HTTP JSON-RPC endpoint that accepts batch requests.
+    batch_url: Option<String>,
+    http: reqwest::Client,
+    blocks: Arc<Mutex<LruCache<u64, Arc<Block<Transaction>>>>>,
+}
+
+#[derive(Deserialize)]
+struct BatchResponse {
+    id: usize,
+    #[serde(default)]
+    result: serde_json::Value,
+    error: Option<serde_json::Value>,
+}
+
+impl<T: JsonRpcClient> ChainClient<T> {
+    /// Wraps `provider`, keeping the last `block_cache_size` fetched blocks in memory.
+    pub fn new(provider: Provider<T>, block_cache_size: usize) -> Self {
+        ChainClient {
+            provider,
+            batch_url: None,
+            http: reqwest::Client::new(),
+            blocks: Arc::new(Mutex::new(LruCache::new(block_cache_size.max(1)))),
+        }
+    }
+
+    /// Sends transaction lookups to `url` as JSON-RPC batches instead of one request each.
+    pub fn with_batch_url(mut self, url: &str) -> Self {
+        self.batch_url = Some(url.to_string());
+        self
+    }
+
+    pub fn provider(&self) -> &Provider<T> {
+        &self.provider
+    }
+
+    pub async fn get_block_number(&self) -> Result<u64> {
+        Ok(self.provider.get_block_number().await?.as_u64())
+    }
+
+    pub async fn get_transaction(&self, hash: H256) -> Result<Option<Transaction>> {
+        let mut txs = self.get_transactions(&[hash]).await?;
+        txs.pop().unwrap_or(Ok(None))
+    }
+
+    /// Looks up the transactions `hashes`, in order. The outer error is a failure of the whole
+    /// request, the inner ones a failure of a single lookup in a batch.
+    pub async fn get_transactions(
+        &self,
+        hashes: &[H256],
+    ) -> Result<Vec<Result<Option<Transaction>>>> {
+        match &self.batch_url {
+            Some(url) if hashes.len() > 1 => self.batch_get_transactions(url, hashes).await,
+            _ => {
+                let lookups = hashes
+                    .iter()
+                    .map(|hash| self.provider.get_transaction(*hash));
+                let txs = futures::future::join_all(lookups).await;
+                Ok(txs.into_iter().map(|tx| Ok(tx?)).collect())
+            }
+        }
+    }
+
+    async fn batch_get_transactions(
+        &self,
+        url: &str,
+        hashes: &[H256],
+    ) -> Result<Vec<Result<Option<Transaction>>>> {
+        let requests: Vec<_> = hashes
+            .iter()
+            .enumerate()
+            .map(|(id, hash)| {
+                json!({"jsonrpc": "2.0", "id": id, "method": "eth_getTransactionByHash", "params": [hash]})
+            })
+            .collect();
+        let responses: Vec<BatchResponse> = self
+            .http
+            .post(url)
+            .json(&requests)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(ProviderError::from)?
+            .json()
+            .await
+            .map_err(ProviderError::from)?;
+
+        let mut txs: Vec<Result<Option<Transaction>>> = hashes
+            .iter()
+            .map(|_| Err(Error::MissingField("batch response")))
+            .collect();
+        for response in responses {
+            let Some(tx) = txs.get_mut(response.id) else {
+                continue;
+            };
+            *tx = match response.error {
+                Some(error) => Err(ProviderError::CustomError(error.to_string()).into()),
+                None => serde_json::from_value(response.result)
+                    .map_err(|error| ProviderError::from(error).into()),
+            };
+        }
+        Ok(txs)
+    }
+
+    pub async fn get_receipt(&self, hash: H256) -> Result<Option<TransactionReceipt>> {
+        Ok(self.provider.get_transaction_receipt(hash).await?)
+    }
+
+    /// Returns the receipts of all transactions in block `number`, in block order.
+    pub async fn get_block_receipts(&self, number: u64) -> Result<Vec<TransactionReceipt>> {
+        Ok(self.provider.get_block_receipts(number).await?)
+    }
+
+    /// Returns block `number` with its full transactions, from the cache if it was fetched before.
+    pub async fn get_block(&self, number: u64) -> Result<Arc<Block<Transaction>>> {
+        if let Some(block) = self.blocks.lock().unwrap().get(&number) {
+            return Ok(block.clone());
+        }
+        let block = self
+            .provider
+            .get_block_with_txs(number)
+            .await?
+            .ok_or(Error::MissingField("block"))?;
+        let block = Arc::new(block);
+        self.blocks.lock().unwrap().insert(number, block.clone());
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::U64;
+
+    #[tokio::test]
+    async fn test_get_block_is_cached() {
+        let (provider, mock) = Provider::mocked();
+        let block = Block::<Transaction> {
+            number: Some(U64::from(7)),
+            ..Default::default()
+        };
+        mock.push(block.clone()).unwrap();
+        let chain = ChainClient::new(provider, 2);
+
+        // Only one response is mocked, so the second call must be served from the cache.
+        assert_eq!(*chain.get_block(7).await.unwrap(), block);
+        assert_eq!(*chain.get_block(7).await.unwrap(), block);
+        assert!(chain.get_block(8).await.is_err());
+    }
+}
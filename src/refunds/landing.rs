@@ -1,38 +1,109 @@
-use ethers::providers::{JsonRpcClient, Middleware, Provider};
-use ethers::types::{Address, Block, Transaction, TxHash, H256};
+use super::{
+    backrun::Backrun, builder::proposer_payment, chain::ChainClient, refund::RefundRecipients,
+};
+use crate::error::{Error, Result};
+use ethers::providers::JsonRpcClient;
+use ethers::types::{Address, Block, Transaction, TransactionReceipt, H256, U256};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(default, rename_all = "camelCase")]
 pub struct Landing {
     pub block: u64,
     pub timestamp: u64,
+    /// Fee recipient of the block.
     pub builder: Address,
+    /// Name of the builder in the [`BuilderRegistry`](super::builder::BuilderRegistry), if known.
+    pub builder_name: Option<String>,
+    /// Transaction of the block paying its proposer.
+    pub proposer_payment: Option<H256>,
+    /// Whether the transaction succeeded, from its receipt. `None` for landings scanned before
+    /// receipts were fetched.
+    pub success: Option<bool>,
+    pub gas_used: Option<u64>,
+    pub effective_gas_price: Option<U256>,
+    /// Position of the transaction in the block.
+    pub tx_index: Option<u64>,
+    /// Searcher transactions that backran the transaction, in block order.
+    pub backruns: Vec<Backrun>,
 }
 
 impl Landing {
+    /// Landing of `tx`, included in `block`, with the outcome recorded in its `receipt`. Backruns
+    /// and the builder name are left empty.
+    pub fn new(
+        tx: &Transaction,
+        block: &Block<Transaction>,
+        receipt: &TransactionReceipt,
+    ) -> Result<Landing> {
+        let gas_used = receipt
+            .gas_used
+            .map(|gas| u64::try_from(gas).map_err(|error| Error::decode("gas used", error)))
+            .transpose()?;
+        Ok(Landing {
+            block: block
+                .number
+                .ok_or(Error::MissingField("block.number"))?
+                .as_u64(),
+            timestamp: block.timestamp.as_u64(),
+            builder: block.author.ok_or(Error::MissingField("block.author"))?,
+            builder_name: None,
+            proposer_payment: proposer_payment(block),
+            // Pre-Byzantium receipts have a state root instead of a status.
+            success: receipt.status.map(|status| status.as_u64() == 1),
+            gas_used,
+            effective_gas_price: receipt.effective_gas_price.or(tx.gas_price),
+            tx_index: Some(receipt.transaction_index.as_u64()),
+            backruns: vec![],
+        })
+    }
+
+    /// Looks up the landing of `target_hash`, with backruns closed by a refund to one of
+    /// `recipients`.
     pub async fn get_landing_for_tx<T: JsonRpcClient>(
         target_hash: H256,
-        eth_client: &Provider<T>,
-    ) -> (Option<Landing>, Option<Transaction>, Option<Block<TxHash>>) {
-        let tx = eth_client.get_transaction(target_hash).await.unwrap();
+        chain: &ChainClient<T>,
+        recipients: &RefundRecipients,
+    ) -> Result<(
+        Option<Landing>,
+        Option<Transaction>,
+        Option<Arc<Block<Transaction>>>,
+    )> {
+        let tx = chain.get_transaction(target_hash).await?;
+        Self::get_landing(tx, chain, recipients).await
+    }
+
+    /// Like [`Landing::get_landing_for_tx`], for a transaction that was already looked up.
+    pub async fn get_landing<T: JsonRpcClient>(
+        tx: Option<Transaction>,
+        chain: &ChainClient<T>,
+        recipients: &RefundRecipients,
+    ) -> Result<(
+        Option<Landing>,
+        Option<Transaction>,
+        Option<Arc<Block<Transaction>>>,
+    )> {
         match tx {
-            Some(tx) => {
-                let block_number = tx.block_number.unwrap().as_u64();
-                let block = eth_client.get_block(block_number).await.unwrap().unwrap();
-                let timestamp = block.timestamp.as_u64();
-                let builder: Address = block.author.unwrap();
-                (
-                    Some(Landing {
-                        block: block_number,
-                        timestamp,
-                        builder,
-                    }),
-                    Some(tx),
-                    Some(block),
-                )
+            // Pending transactions are returned without a block number.
+            Some(
+                tx @ Transaction {
+                    block_number: Some(block_number),
+                    ..
+                },
+            ) => {
+                let block = chain.get_block(block_number.as_u64()).await?;
+                let receipt = chain
+                    .get_receipt(tx.hash)
+                    .await?
+                    .ok_or(Error::MissingField("receipt"))?;
+                let landing = Landing {
+                    backruns: Backrun::get_backruns(&tx, &block, recipients, chain).await?,
+                    ..Self::new(&tx, &block, &receipt)?
+                };
+                Ok((Some(landing), Some(tx), Some(block)))
             }
-            None => (None, None, None),
+            _ => Ok((None, None, None)),
         }
     }
 }
@@ -41,22 +112,59 @@ impl Landing {
 mod tests {
     use super::*;
     use dotenv::dotenv;
-    use ethers::providers::{Http, Middleware, Provider};
+    use ethers::providers::{Http, Provider};
     use ethers::types::{Address, H256};
     use std::str::FromStr;
 
+    #[test]
+    fn test_landing_from_receipt() {
+        let builder = Address::random();
+        let block = Block {
+            number: Some(10.into()),
+            timestamp: 120.into(),
+            author: Some(builder),
+            ..Default::default()
+        };
+        let receipt = TransactionReceipt {
+            transaction_index: 4.into(),
+            status: Some(0.into()),
+            gas_used: Some(50_000.into()),
+            effective_gas_price: Some(7.into()),
+            ..Default::default()
+        };
+        let landing = Landing::new(&Transaction::default(), &block, &receipt).unwrap();
+        assert_eq!(
+            landing,
+            Landing {
+                block: 10,
+                timestamp: 120,
+                builder,
+                builder_name: None,
+                proposer_payment: None,
+                success: Some(false),
+                gas_used: Some(50_000),
+                effective_gas_price: Some(7.into()),
+                tx_index: Some(4),
+                backruns: vec![],
+            }
+        );
+    }
+
     #[tokio::test]
     async fn test_get_landing_for_tx() {
         dotenv().ok();
         let rpc = std::env::var("RPC_URL").expect("RPC_URL not set");
         let provider = Provider::<Http>::try_from(rpc).unwrap();
         // let provider = Provider::<Ipc>::connect_ipc("tmp/reth.ipc").await.unwrap();
+        let chain = ChainClient::new(provider, 1);
         let landing = Landing::get_landing_for_tx(
             H256::from_str("0x604a87e9837c45ea4289089bfa22f97a0c91ee7e3d88da2bef59ebf35322092f")
                 .unwrap(),
-            &provider,
+            &chain,
+            &RefundRecipients::default(),
         )
-        .await;
+        .await
+        .unwrap();
 
         match landing.0 {
             Some(landing) => {
@@ -67,6 +175,7 @@ mod tests {
                         timestamp: 1688835419,
                         builder: Address::from_str("0xdafea492d9c6733ae3d56b7ed1adb60692c98bc5")
                             .unwrap(),
+                        ..landing.clone()
                     }
                 );
             }
@@ -1,2 +1,23 @@
+pub mod backrun;
+pub mod builder;
+pub mod chain;
 pub mod landing;
 pub mod refund;
+
+#[cfg(test)]
+pub(crate) fn test_tx(
+    index: u64,
+    from: ethers::types::Address,
+    to: ethers::types::Address,
+) -> ethers::types::Transaction {
+    use ethers::types::{Transaction, H256, U256, U64};
+
+    Transaction {
+        hash: H256::random(),
+        transaction_index: Some(U64::from(index)),
+        from,
+        to: Some(to),
+        value: U256::from(1_000),
+        ..Default::default()
+    }
+}
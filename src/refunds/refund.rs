@@ -1,43 +1,412 @@
-use ethers::providers::{JsonRpcClient, Middleware, Provider};
-use ethers::types::{Block, Transaction, TxHash, H256};
+use crate::{
+    config::ScanConfig,
+    error::{Error, Result},
+};
+use ethers::types::{Address, Block, Transaction, H256, U256};
 use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 #[serde(default, rename_all = "camelCase")]
 pub struct Refund {
     pub signal_tx: H256,
     pub refund_tx: H256,
-    pub value: u64,
+    /// Refunded wei, stored as a decimal string.
+    #[serde(with = "decimal")]
+    pub value: U256,
+    /// Address the refund was paid to.
+    pub recipient: Address,
+    /// Why `recipient` was accepted as a refund recipient of the signal tx.
+    pub rule: RefundRule,
+}
+
+/// Source of a candidate refund recipient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RefundRule {
+    /// The sender of the signal tx.
+    #[default]
+    Sender,
+    /// A refund address of the sender in `scan.refund_addresses` in the config.
+    Config,
+    /// A refund address of the sender in the refund addresses file.
+    File,
+}
+
+impl RefundRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RefundRule::Sender => "sender",
+            RefundRule::Config => "config",
+            RefundRule::File => "file",
+        }
+    }
+}
+
+impl std::str::FromStr for RefundRule {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "sender" => Ok(RefundRule::Sender),
+            "config" => Ok(RefundRule::Config),
+            "file" => Ok(RefundRule::File),
+            _ => Err(Error::decode("refund rule", value)),
+        }
+    }
+}
+
+/// Addresses accepted as refund recipients of the txs of a sender, in addition to the sender
+/// itself, e.g. the refund addresses that wallets set on their MEV-Share transactions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RefundRecipients {
+    /// Rule of each (sender, refund address) pair.
+    addresses: HashMap<(Address, Address), RefundRule>,
+}
+
+impl RefundRecipients {
+    /// Accepts the refund addresses of `config.refund_addresses` and of the refund addresses file.
+    pub fn from_config(config: &ScanConfig) -> Result<Self> {
+        let pairs = config
+            .refund_addresses
+            .iter()
+            .flat_map(|(sender, addresses)| addresses.iter().map(|address| (*sender, *address)));
+        let mut recipients = RefundRecipients::default().with_addresses(pairs, RefundRule::Config);
+        if let Some(path) = &config.refund_addresses_file {
+            recipients = recipients.with_addresses(Self::read_file(path)?, RefundRule::File);
+        }
+        Ok(recipients)
+    }
+
+    /// Accepts the refund address of each (sender, refund address) pair as a recipient of the txs
+    /// of the sender, recorded with `rule`. Pairs added first keep their rule.
+    pub fn with_addresses(
+        mut self,
+        pairs: impl IntoIterator<Item = (Address, Address)>,
+        rule: RefundRule,
+    ) -> Self {
+        for pair in pairs {
+            self.addresses.entry(pair).or_insert(rule);
+        }
+        self
+    }
+
+    /// Reads (sender, refund address) pairs from `path`, one pair of whitespace-separated
+    /// addresses per line. Empty lines and `#` comments are skipped.
+    pub fn read_file(path: &Path) -> Result<Vec<(Address, Address)>> {
+        std::fs::read_to_string(path)?
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let [sender, address] = fields[..] else {
+                    return Err(Error::decode("refund addresses line", line));
+                };
+                let parse = |address: &str| {
+                    address.parse().map_err(|error| {
+                        Error::decode(format!("refund address {}", address), error)
+                    })
+                };
+                Ok((parse(sender)?, parse(address)?))
+            })
+            .collect()
+    }
+
+    /// Returns the rule under which `recipient` is a refund recipient of a tx sent by `sender`.
+    pub fn rule(&self, sender: Address, recipient: Address) -> Option<RefundRule> {
+        if recipient == sender {
+            return Some(RefundRule::Sender);
+        }
+        self.addresses.get(&(sender, recipient)).copied()
+    }
 }
 
 impl Refund {
-    pub async fn scan_refund<T: JsonRpcClient>(
+    /// Looks for refunds of `tx` in its bundle in `block`: transfers from the builder to the sender
+    /// of `tx` or to one of its refund addresses, after the backruns that directly follow `tx`.
+    /// Every backrun of `tx` can trigger its own refund, so the whole run of refunds is returned,
+    /// in block order.
+    ///
+    /// Bundle boundaries are not visible onchain: the bundle ends at the first transaction after
+    /// the refunds that is not one, and has no refunds when a transaction of the builder or of the
+    /// sender of `tx` comes before any refund.
+    pub fn scan_refunds(
+        tx: &Transaction,
+        block: &Block<Transaction>,
+        recipients: &RefundRecipients,
+    ) -> Result<Vec<Refund>> {
+        let txn_index = tx
+            .transaction_index
+            .ok_or(Error::MissingField("transaction.transactionIndex"))?
+            .as_usize();
+        let txns = block.transactions.get(txn_index + 1..).unwrap_or_default();
+        let builder = block.author.ok_or(Error::MissingField("block.author"))?;
+
+        let mut refunds = vec![];
+        for txn in txns {
+            match Self::paid_by(txn, tx, builder, recipients) {
+                Some(refund) => refunds.push(refund),
+                None if !refunds.is_empty() => break,
+                None if txn.from == builder || txn.from == tx.from => break,
+                // A backrun of `tx`.
+                None => {}
+            }
+        }
+        Ok(refunds)
+    }
+
+    /// Returns the refund of `tx` that `txn` pays, if it is a transfer from `builder` to the sender
+    /// of `tx` or to one of its refund addresses.
+    pub fn paid_by(
+        txn: &Transaction,
         tx: &Transaction,
-        block: &Block<TxHash>,
-        eth_client: &Provider<T>,
+        builder: Address,
+        recipients: &RefundRecipients,
     ) -> Option<Refund> {
-        let txn_index = tx.transaction_index.unwrap().as_u64();
-        let txns = &block.transactions.as_slice()[(txn_index + 1) as usize..];
-        let from = tx.from; // User
-        let builder = block.author.unwrap();
-
-        // Check for refund txn from builder to user
-        for txn_hash in txns {
-            // Get the txn from the hash
-            let txn = eth_client
-                .get_transaction(*txn_hash)
-                .await
-                .unwrap()
-                .unwrap();
-            if txn.from == builder && txn.to.unwrap() == from {
-                return Some(Refund {
-                    signal_tx: tx.hash,
-                    refund_tx: txn.hash,
-                    value: txn.value.as_u64(),
-                });
+        if txn.from != builder {
+            return None;
+        }
+        // Contract creations have no `to` and are never refunds.
+        let to = txn.to?;
+        let rule = recipients.rule(tx.from, to)?;
+        Some(Refund {
+            signal_tx: tx.hash,
+            refund_tx: txn.hash,
+            value: txn.value,
+            recipient: to,
+            rule,
+        })
+    }
+}
+
+/// (De)serializes a [`U256`] as a decimal string, so stored values stay readable and sortable
+/// by length. Integers are accepted too, as refunds were stored as 64-bit values before.
+pub(crate) mod decimal {
+    use ethers::types::U256;
+    use serde::{de, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = U256;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a decimal string or an unsigned integer")
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<U256, E> {
+                Ok(U256::from(value))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<U256, E> {
+                u64::try_from(value)
+                    .map(U256::from)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Signed(value), &self))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<U256, E> {
+                U256::from_dec_str(value).map_err(E::custom)
             }
         }
 
-        None
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::refunds::test_tx;
+    use ethers::types::H160;
+
+    #[test]
+    fn test_scan_refunds() {
+        let (user, builder, searcher) = (H160::random(), H160::random(), H160::random());
+        let target = test_tx(1, user, H160::random());
+        let refunds = [test_tx(4, builder, user), test_tx(5, builder, user)];
+        let block = Block {
+            author: Some(builder),
+            transactions: vec![
+                // A builder payment to the user before the target is not its refund.
+                test_tx(0, builder, user),
+                target.clone(),
+                test_tx(2, searcher, H160::random()),
+                test_tx(3, searcher, H160::random()),
+                refunds[0].clone(),
+                refunds[1].clone(),
+                // After the refunds, the bundle of the target has ended.
+                test_tx(6, H160::random(), H160::random()),
+                test_tx(7, builder, user),
+            ],
+            ..Default::default()
+        };
+
+        let recipients = RefundRecipients::default();
+        let expected: Vec<_> = refunds
+            .iter()
+            .map(|refund| Refund {
+                signal_tx: target.hash,
+                refund_tx: refund.hash,
+                value: U256::from(1_000),
+                recipient: user,
+                rule: RefundRule::Sender,
+            })
+            .collect();
+        assert_eq!(
+            Refund::scan_refunds(&target, &block, &recipients).unwrap(),
+            expected
+        );
+        assert_eq!(
+            Refund::scan_refunds(&refunds[1], &block, &recipients).unwrap(),
+            vec![]
+        );
+
+        // A builder transaction to someone else ends the bundle before any refund.
+        let mut paid = block.clone();
+        paid.transactions[2] = test_tx(2, builder, H160::random());
+        assert_eq!(
+            Refund::scan_refunds(&target, &paid, &recipients).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_scan_refunds_of_two_targets() {
+        let (user, other, builder, refund_address) = (
+            H160::random(),
+            H160::random(),
+            H160::random(),
+            H160::random(),
+        );
+        let refund_txs = |block: &Block<Transaction>, target: &Transaction, recipients| {
+            Refund::scan_refunds(target, block, recipients)
+                .unwrap()
+                .into_iter()
+                .map(|refund| refund.refund_tx)
+                .collect::<Vec<_>>()
+        };
+
+        // Two targets of the same sender, each refunded after its own backrun.
+        let targets = [
+            test_tx(0, user, H160::random()),
+            test_tx(3, user, H160::random()),
+        ];
+        let refunds = [test_tx(2, builder, user), test_tx(5, builder, user)];
+        let block = Block {
+            author: Some(builder),
+            transactions: vec![
+                targets[0].clone(),
+                test_tx(1, H160::random(), H160::random()),
+                refunds[0].clone(),
+                targets[1].clone(),
+                test_tx(4, H160::random(), H160::random()),
+                refunds[1].clone(),
+            ],
+            ..Default::default()
+        };
+        let recipients = RefundRecipients::default();
+        for (target, refund) in targets.iter().zip(&refunds) {
+            assert_eq!(refund_txs(&block, target, &recipients), vec![refund.hash]);
+        }
+
+        // Two targets of different senders sharing a refund address.
+        let targets = [
+            test_tx(0, user, H160::random()),
+            test_tx(3, other, H160::random()),
+        ];
+        let refunds = [
+            test_tx(2, builder, refund_address),
+            test_tx(5, builder, refund_address),
+        ];
+        let block = Block {
+            author: Some(builder),
+            transactions: vec![
+                targets[0].clone(),
+                test_tx(1, H160::random(), H160::random()),
+                refunds[0].clone(),
+                targets[1].clone(),
+                test_tx(4, H160::random(), H160::random()),
+                refunds[1].clone(),
+            ],
+            ..Default::default()
+        };
+        let recipients = RefundRecipients::default().with_addresses(
+            [(user, refund_address), (other, refund_address)],
+            RefundRule::Config,
+        );
+        for (target, refund) in targets.iter().zip(&refunds) {
+            assert_eq!(refund_txs(&block, target, &recipients), vec![refund.hash]);
+        }
+    }
+
+    #[test]
+    fn test_scan_refund_to_refund_address() {
+        let (user, builder, refund_address) = (H160::random(), H160::random(), H160::random());
+        let target = test_tx(0, user, H160::random());
+        let refund = test_tx(1, builder, refund_address);
+        let block = Block {
+            author: Some(builder),
+            transactions: vec![target.clone(), refund.clone()],
+            ..Default::default()
+        };
+
+        let scan = |recipients: &RefundRecipients| {
+            Refund::scan_refunds(&target, &block, recipients)
+                .unwrap()
+                .into_iter()
+                .map(|refund| (refund.recipient, refund.rule))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(scan(&RefundRecipients::default()), vec![]);
+        let recipients = RefundRecipients::default()
+            .with_addresses([(user, refund_address)], RefundRule::File)
+            .with_addresses([(user, refund_address)], RefundRule::Config);
+        assert_eq!(scan(&recipients), vec![(refund_address, RefundRule::File)]);
+
+        // A refund address of another sender is not a recipient of the user's txs.
+        let recipients = RefundRecipients::default()
+            .with_addresses([(H160::random(), refund_address)], RefundRule::Config);
+        assert_eq!(scan(&recipients), vec![]);
+    }
+
+    #[test]
+    fn test_read_refund_addresses_file() {
+        let path = std::env::temp_dir().join(format!("refund-addresses-{}.txt", H256::random()));
+        let (sender, address) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        std::fs::write(
+            &path,
+            format!("# sender refund_address\n\n{:?} {:?}\n", sender, address),
+        )
+        .unwrap();
+        assert_eq!(
+            RefundRecipients::read_file(&path).unwrap(),
+            vec![(sender, address)]
+        );
+
+        std::fs::write(&path, format!("{:?}\n", address)).unwrap();
+        assert!(RefundRecipients::read_file(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_refund_value_serde() {
+        // 100 ETH, more than fits in a u64.
+        let value = U256::exp10(20);
+        let refund = Refund {
+            value,
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&refund).unwrap();
+        assert_eq!(json["value"], "100000000000000000000");
+        assert_eq!(serde_json::from_value::<Refund>(json).unwrap(), refund);
+
+        let legacy: Refund = serde_json::from_str(r#"{"value": 1000}"#).unwrap();
+        assert_eq!(legacy.value, U256::from(1_000));
     }
 }